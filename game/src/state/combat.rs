@@ -1,12 +1,12 @@
 use crate::state::combat::anim::GemAnimation;
 use crate::state::combat::board::{Board, BoardState};
 use crate::state::combat::gem::{Gem, GemColor};
-use luck::pool::RandomPool;
 use luck::LuckState;
 use match3::rect_board::RectBoard;
 use match3::{BoardMatch, SimpleGem};
 use math::board::CellIndex;
-use model::{GemColorId, Registry};
+use model::scrapcore_serialization::registry::index::RegistryIndex;
+use model::{RandomTableItem, RandomTableModel, Registry};
 
 pub mod gem;
 
@@ -36,12 +36,14 @@ impl CombatState {
             board: RectBoard::from_fn(6, 5, |_| SimpleGem(GemColor::Empty)),
             state: BoardState::Idle,
         };
-        let colors: Vec<GemColorId> = registry.gem_color.ids().collect();
-
-        let pool = RandomPool::equal(colors);
+        let spawns =
+            RegistryIndex::<RandomTableModel>::get(&registry.settings.gem_spawns, registry);
 
         for gem in board.board.board.iter_mut() {
-            gem.0 = GemColor::Color(*pool.get(&mut luck))
+            gem.0 = match spawns.roll(registry, &mut luck) {
+                RandomTableItem::GemColor { id } => GemColor::Color(id),
+                item => unreachable!("Gem spawns table should only roll gem colors, got {item:?}"),
+            }
         }
 
         Self {
@@ -3,10 +3,11 @@ use macroquad::color::{Color, WHITE};
 use strum::EnumIs;
 
 use math::arc::{arc_angles, arc_center_radius};
-use math::board::{CellIndex, GridMath};
+use math::board::{CellIndex, GridMath, GridMetric};
 use math::glam::{FloatExt, Vec2};
 use math::glamour::{Point2, Rect, Unit, Vector2};
 use math::gravity::{fall_time, height_at_fall_progress};
+use math::tween::{Animation, Timeline, Tween};
 use with_setter_macro::WithSetters;
 use yakui_tweak::slider::tweak_slider;
 use yakui_tweak::tweak;
@@ -67,7 +68,7 @@ impl GemMovement {
             GemMovement::Still => f64::INFINITY,
             GemMovement::Held(_) => 0.0,
             GemMovement::Swap(swap) => {
-                let distance = grid.grid_distance(swap.from, swap.to);
+                let distance = grid.metric_distance(swap.from, swap.to, GridMetric::Chebyshev);
                 distance as f64 / tweak_slider("board.swapSpeed", 12.5, 0.1, 20.0)
             }
             GemMovement::Fall(fall) => fall_time(fall.height as f32, fall_gravity()) as f64,
@@ -227,13 +228,23 @@ impl GemAnimation {
             layer: 0,
             tint: WHITE,
         };
-        let progress = ((time - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
+        let progress = self.progress(time);
 
-        self.movement.layout(&mut ui, grid, progress as f32);
-        self.visuals.visuals(&mut ui, progress as f32);
+        self.movement.layout(&mut ui, grid, progress);
+        self.visuals.visuals(&mut ui, progress);
         ui
     }
 
+    /// Eased progress of the animation at the given time
+    pub fn progress(&self, time: f64) -> f32 {
+        let duration = (self.end - self.start) as f32;
+        Timeline::new(
+            self.start,
+            Tween::new(0.0, 1.0, duration).with_easing(self.easing),
+        )
+        .sample_at(time)
+    }
+
     pub fn replace_if_still(&mut self, anim: impl FnOnce() -> Self) {
         if let GemMovement::Still = self.movement {
             *self = anim()
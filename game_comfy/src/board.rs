@@ -230,7 +230,7 @@ impl BoardScene {
             BoardState::Idle => {}
             BoardState::Moving(_) => {}
             BoardState::Refilling(refilling) => {
-                let actions = GravityRefill::refill(&self.board.board, self.board.vertical_lines());
+                let actions = GravityRefill::refill(&self.board.board, self.board.gravity_lines());
                 for action in actions {
                     action.apply(&mut self.board.board, |_| random_gem());
                     let height = action.height();
@@ -8,6 +8,7 @@ use egui_tweak::tweak;
 use egui_tweak::ui::tweak_ui;
 use math::arc::{arc_angles, arc_center_radius};
 use math::gravity::{fall_time, height_at_fall_progress};
+use math::tween::{Animation, Timeline, Tween};
 use with_setter_macro::WithSetters;
 
 use crate::board::gem::{draw_gem, Gem};
@@ -194,10 +195,20 @@ impl GemAnimation {
     }
 
     pub fn update(&self, gem: &Gem, ui: Ui, grid: &GridMath, time: f64) {
-        let progress = ((time - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
+        let progress = self.progress(time);
 
-        let ui = self.movement.ui(ui, grid, progress as f32);
-        self.visuals.draw(gem, ui, progress as f32);
+        let ui = self.movement.ui(ui, grid, progress);
+        self.visuals.draw(gem, ui, progress);
+    }
+
+    /// Eased progress of the animation at the given time
+    pub fn progress(&self, time: f64) -> f32 {
+        let duration = (self.end - self.start) as f32;
+        Timeline::new(
+            self.start,
+            Tween::new(0.0, 1.0, duration).with_easing(self.easing),
+        )
+        .sample_at(time)
     }
 
     pub fn replace_if_still(&mut self, anim: impl FnOnce() -> Self) {
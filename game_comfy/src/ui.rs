@@ -202,7 +202,8 @@ impl GridMath {
         self.distance2(a, b).sqrt()
     }
 
-    /// Returns the grid (manhattan) distance between two cells
+    /// Returns the grid (chebyshev) distance between two cells, where
+    /// diagonal steps are allowed
     pub fn grid_distance(&self, a: usize, b: usize) -> usize {
         let (ax, ay) = self.delinearize(a);
         let (bx, by) = self.delinearize(b);
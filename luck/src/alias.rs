@@ -0,0 +1,80 @@
+use rand::Rng;
+
+use crate::LuckState;
+
+/// Precomputed table for picking weighted indices in constant time, using
+/// the alias method
+///
+/// All math is done in integers, so probabilities match the weights exactly
+#[derive(Debug, Clone, Default)]
+pub struct AliasTable {
+    /// Threshold below which the bucket picks its own index, out of `sum`
+    thresholds: Vec<u128>,
+    /// Index picked when the roll is above the threshold
+    aliases: Vec<usize>,
+    sum: u64,
+}
+
+impl AliasTable {
+    /// Builds the table in linear time
+    ///
+    /// # Panics
+    /// Panics if the sum of the weights overflows
+    pub fn new(weights: &[u64]) -> Self {
+        let sum = weights
+            .iter()
+            .try_fold(0u64, |sum, &w| sum.checked_add(w))
+            .expect("Sum of the weights should fit into u64");
+        let len = weights.len() as u128;
+        let full = sum as u128;
+
+        // Every bucket holds exactly `sum` worth of weight after scaling
+        let mut scaled: Vec<u128> = weights.iter().map(|&w| w as u128 * len).collect();
+        let mut thresholds = vec![full; weights.len()];
+        let mut aliases: Vec<usize> = (0..weights.len()).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..weights.len()).partition(|&i| scaled[i] < full);
+
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            thresholds[s] = scaled[s];
+            aliases[s] = l;
+            scaled[l] = scaled[l] + scaled[s] - full;
+            if scaled[l] < full {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        Self {
+            thresholds,
+            aliases,
+            sum,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.thresholds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.thresholds.is_empty()
+    }
+
+    /// Sum of all weights
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Picks a random index, with probability proportional to its weight
+    pub fn sample(&self, state: &mut LuckState) -> usize {
+        let bucket = state.state.gen_range(0..self.thresholds.len());
+        let roll = state.state.gen_range(0..self.sum) as u128;
+        if roll < self.thresholds[bucket] {
+            bucket
+        } else {
+            self.aliases[bucket]
+        }
+    }
+}
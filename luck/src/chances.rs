@@ -1,3 +1,6 @@
+use std::cmp::Ordering;
+
+use crate::modifier::{modified_draw, LuckModifier, LuckStreak};
 use crate::LuckState;
 use rand::Rng;
 
@@ -16,3 +19,59 @@ pub fn chances(state: &mut LuckState, weights: &[u64], sum: Option<u64>) -> usiz
 
     unreachable!("Generated value should always be lower than the sum of weights")
 }
+
+/// Same as [chances], but only picks among indices allowed by the
+/// predicate, keeping their relative weights
+///
+/// Returns [None] when all indices with non-zero weight are excluded
+pub fn chances_filtered(
+    state: &mut LuckState,
+    weights: &[u64],
+    allowed: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let sum: u64 = weights
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| allowed(*i))
+        .map(|(_, w)| w)
+        .sum();
+    if sum == 0 {
+        return None;
+    }
+
+    let generated = state.state.gen_range(0..sum);
+
+    let mut total = 0;
+    for (i, x) in weights.iter().enumerate() {
+        if !allowed(i) {
+            continue;
+        }
+        total += x;
+        if generated < total {
+            return Some(i);
+        }
+    }
+
+    unreachable!("Generated value should always be lower than the sum of weights")
+}
+
+/// Same as [chances], but with luck modifier applied
+///
+/// `better` compares outcomes by their indices, returning
+/// [Ordering::Greater] when the first one is more favourable. `streak` is
+/// only needed for modifiers with a streak limit
+pub fn chances_with(
+    state: &mut LuckState,
+    weights: &[u64],
+    sum: Option<u64>,
+    modifier: &LuckModifier,
+    streak: Option<&mut LuckStreak>,
+    better: impl Fn(usize, usize) -> Ordering,
+) -> usize {
+    if modifier.is_neutral() {
+        return chances(state, weights, sum);
+    }
+    modified_draw(state, weights, modifier, streak, better, |state| {
+        chances(state, weights, sum)
+    })
+}
@@ -0,0 +1,99 @@
+use rand::Rng;
+
+use crate::LuckState;
+
+/// Weights stored in a Fenwick tree, allowing to both change a weight and
+/// pick a weighted index in logarithmic time
+#[derive(Debug, Clone, Default)]
+pub struct FenwickTree {
+    weights: Vec<u64>,
+    /// One-based tree of partial sums
+    tree: Vec<u64>,
+}
+
+impl FenwickTree {
+    /// Builds the tree in linear time
+    pub fn new(weights: &[u64]) -> Self {
+        let mut tree = vec![0; weights.len() + 1];
+        tree[1..].copy_from_slice(weights);
+        for i in 1..tree.len() {
+            let parent = i + lowest_bit(i);
+            if parent < tree.len() {
+                tree[parent] += tree[i];
+            }
+        }
+        Self {
+            weights: weights.to_vec(),
+            tree,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    pub fn weights(&self) -> &[u64] {
+        &self.weights
+    }
+
+    /// Sum of all weights
+    pub fn sum(&self) -> u64 {
+        self.prefix_sum(self.weights.len())
+    }
+
+    /// Sum of the first `count` weights
+    pub fn prefix_sum(&self, count: usize) -> u64 {
+        let mut i = count;
+        let mut sum = 0;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= lowest_bit(i);
+        }
+        sum
+    }
+
+    /// Changes the weight at the given index
+    pub fn set(&mut self, index: usize, weight: u64) {
+        let old = std::mem::replace(&mut self.weights[index], weight);
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i] - old + weight;
+            i += lowest_bit(i);
+        }
+    }
+
+    /// Returns the index of the weight that covers `value`, when weights are
+    /// laid out one after another
+    ///
+    /// # Panics
+    /// Panics in debug mode if the value is not lower than the sum
+    pub fn find(&self, value: u64) -> usize {
+        debug_assert!(value < self.sum(), "Value should be lower than the sum");
+        let mut pos = 0;
+        let mut remaining = value;
+        let mut step = self.tree.len().next_power_of_two() / 2;
+        while step > 0 {
+            let next = pos + step;
+            if next < self.tree.len() && self.tree[next] <= remaining {
+                pos = next;
+                remaining -= self.tree[next];
+            }
+            step /= 2;
+        }
+        pos
+    }
+
+    /// Picks a random index, with probability proportional to its weight
+    pub fn sample(&self, state: &mut LuckState) -> usize {
+        let generated = state.state.gen_range(0..self.sum());
+        self.find(generated)
+    }
+}
+
+fn lowest_bit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
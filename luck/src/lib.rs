@@ -2,11 +2,27 @@
 use rand::{RngCore, SeedableRng};
 use rand_pcg::Pcg32;
 
+pub mod alias;
 pub mod chances;
+pub mod fenwick;
+pub mod modifier;
+pub mod pity;
 pub mod pool;
+pub mod replay;
+pub mod shuffle;
+#[cfg(feature = "test-support")]
+pub mod stats;
+pub mod weight;
 
-#[derive(Debug)]
+#[cfg(test)]
+mod tests;
+
+/// Random state, which can be saved, restored and forked into independent
+/// sub-streams
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LuckState {
+    seed: u64,
     state: Pcg32,
 }
 
@@ -17,7 +33,37 @@ impl LuckState {
 
     pub fn new(seed: u64) -> Self {
         Self {
+            seed,
             state: Pcg32::seed_from_u64(seed),
         }
     }
+
+    /// Seed this state was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Derives an independent state for a subsystem, like board refill or
+    /// enemy AI
+    ///
+    /// Forks only depend on the seed and the label, so rolling the parent
+    /// state or other forks doesn't change the resulting sequence
+    pub fn fork(&self, label: &str) -> Self {
+        Self::new(split_mix64(self.seed ^ fnv1a64(label.as_bytes())))
+    }
+}
+
+/// Platform-independent FNV-1a hash, used to turn fork labels into seeds
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// SplitMix64 finalizer, spreading similar seeds far apart
+fn split_mix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
 }
@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+use crate::chances::chances;
+use crate::LuckState;
+
+/// Luck alterations applied to a draw, like ones granted by items or status
+/// effects
+///
+/// Modifiers are composable, adding two of them stacks their effects.
+/// Whether an outcome is favourable is decided by the caller-supplied
+/// ordering, where [Ordering::Greater] means the first outcome is better
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LuckModifier {
+    /// Number of extra rolls, keeping the best one when positive and the
+    /// worst one when negative
+    pub advantage: i32,
+    /// Percent by which the weight of the best outcome is increased and the
+    /// weight of the worst one is decreased, outcomes in between are shifted
+    /// proportionally to their rank. Negative values favour worse outcomes
+    pub luck: i32,
+    /// Maximum number of bad draws in a row, see [LuckStreak]
+    pub streak_limit: Option<u32>,
+}
+
+impl LuckModifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Modifier granting `rolls` extra rolls and keeping the best one
+    pub fn advantage(rolls: u32) -> Self {
+        Self::new().with_advantage(rolls as i32)
+    }
+
+    /// Modifier granting `rolls` extra rolls and keeping the worst one
+    pub fn disadvantage(rolls: u32) -> Self {
+        Self::new().with_advantage(-(rolls as i32))
+    }
+
+    pub fn with_advantage(mut self, advantage: i32) -> Self {
+        self.advantage = advantage;
+        self
+    }
+
+    pub fn with_luck(mut self, luck: i32) -> Self {
+        self.luck = luck;
+        self
+    }
+
+    pub fn with_streak_limit(mut self, streak_limit: impl Into<Option<u32>>) -> Self {
+        self.streak_limit = streak_limit.into();
+        self
+    }
+
+    /// Checks whenever the modifier leaves draws unaltered
+    pub fn is_neutral(&self) -> bool {
+        self.advantage == 0 && self.luck == 0 && self.streak_limit.is_none()
+    }
+
+    /// Returns weights shifted by the luck factor, given doubled ranks of
+    /// each outcome from [outcome_ranks]
+    fn shift_weights(&self, weights: &[u64], ranks: &[i64]) -> Vec<u64> {
+        let spread = weights.len().saturating_sub(1) as i128;
+        if self.luck == 0 || spread == 0 {
+            return weights.to_vec();
+        }
+        // Prevent sum of the weights from overflowing
+        let cap = (u64::MAX / weights.len() as u64) as i128;
+        weights
+            .iter()
+            .zip(ranks)
+            .map(|(&weight, &rank)| {
+                let percent = 100 * spread + self.luck as i128 * rank as i128;
+                (weight as i128 * percent / (100 * spread)).clamp(0, cap) as u64
+            })
+            .collect()
+    }
+}
+
+impl Add for LuckModifier {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            advantage: self.advantage.saturating_add(rhs.advantage),
+            luck: self.luck.saturating_add(rhs.luck),
+            streak_limit: match (self.streak_limit, rhs.streak_limit) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+impl AddAssign for LuckModifier {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for LuckModifier {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Number of bad draws in a row, used by [LuckModifier::streak_limit]
+///
+/// A draw is bad when its outcome ranks in the worse half of all outcomes.
+/// Once the limit is reached, the next draw only picks from the better
+/// half
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LuckStreak {
+    bad: u32,
+}
+
+impl LuckStreak {
+    pub fn bad_draws(&self) -> u32 {
+        self.bad
+    }
+
+    pub fn reset(&mut self) {
+        self.bad = 0;
+    }
+}
+
+/// Ranks of outcomes, doubled and centered around zero, so the worst outcome
+/// gets `1 - len`, the best one gets `len - 1`, and equal outcomes share the
+/// same rank
+fn outcome_ranks(len: usize, better: &impl Fn(usize, usize) -> Ordering) -> Vec<i64> {
+    let mut order: Vec<usize> = (0..len).collect();
+    order.sort_by(|&a, &b| better(a, b));
+    let mut ranks = vec![0; len];
+    let mut start = 0;
+    while start < len {
+        let mut end = start + 1;
+        while end < len && better(order[start], order[end]) == Ordering::Equal {
+            end += 1;
+        }
+        // Sum of the first and the last position of the group of equals
+        let rank = (start + end - 1) as i64 - (len as i64 - 1);
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Draws an index with the modifier applied
+///
+/// `plain` is used for draws that don't need altered weights, allowing pools
+/// to use their faster samplers
+pub(crate) fn modified_draw(
+    state: &mut LuckState,
+    weights: &[u64],
+    modifier: &LuckModifier,
+    streak: Option<&mut LuckStreak>,
+    better: impl Fn(usize, usize) -> Ordering,
+    plain: impl Fn(&mut LuckState) -> usize,
+) -> usize {
+    let streak = streak.filter(|_| modifier.streak_limit.is_some());
+    let ranks =
+        (modifier.luck != 0 || streak.is_some()).then(|| outcome_ranks(weights.len(), &better));
+
+    let shifted = ranks.as_ref().map(|ranks| {
+        let mut shifted = modifier.shift_weights(weights, ranks);
+        let limit_reached = streak
+            .as_ref()
+            .zip(modifier.streak_limit)
+            .is_some_and(|(streak, limit)| streak.bad >= limit);
+        let has_good = shifted.iter().zip(ranks).any(|(&w, &r)| w > 0 && r >= 0);
+        if limit_reached && has_good {
+            for (weight, &rank) in shifted.iter_mut().zip(ranks) {
+                if rank < 0 {
+                    *weight = 0;
+                }
+            }
+        }
+        shifted
+    });
+    // Strong bad luck can take away all of the weight, leaving only the
+    // outcomes that were possible without it
+    let shifted = shifted.filter(|shifted| shifted.iter().any(|&w| w > 0));
+    let draw = |state: &mut LuckState| match &shifted {
+        Some(shifted) => chances(state, shifted, None),
+        None => plain(state),
+    };
+
+    let mut picked = draw(state);
+    let keep = if modifier.advantage > 0 {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    };
+    for _ in 0..modifier.advantage.unsigned_abs() {
+        let other = draw(state);
+        if better(other, picked) == keep {
+            picked = other;
+        }
+    }
+
+    if let (Some(streak), Some(ranks)) = (streak, &ranks) {
+        if ranks[picked] < 0 {
+            streak.bad = streak.bad.saturating_add(1);
+        } else {
+            streak.bad = 0;
+        }
+    }
+
+    picked
+}
@@ -0,0 +1,225 @@
+use crate::chances::chances;
+use crate::pool::RandomPool;
+use crate::LuckState;
+
+/// How the weight of an item grows while the item is not picked
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PityGrowth {
+    /// Weight never changes
+    #[default]
+    None,
+    /// Adds `step` to the weight for each miss
+    Linear { step: u64 },
+    /// Increases the weight by `percent` of its current value for each miss
+    Exponential { percent: u64 },
+}
+
+impl PityGrowth {
+    /// Returns the weight of an item after the given number of misses
+    ///
+    /// Items with zero base weight are never meant to be picked, so their
+    /// weight never grows
+    pub fn weight(&self, base: u64, misses: u32) -> u64 {
+        if base == 0 {
+            return 0;
+        }
+        match *self {
+            PityGrowth::None => base,
+            PityGrowth::Linear { step } => base.saturating_add(step.saturating_mul(misses as u64)),
+            PityGrowth::Exponential { percent } => {
+                // Fixed point, so small weights can grow too
+                let max = (u64::MAX as u128) << 32;
+                let mut weight = (base as u128) << 32;
+                for _ in 0..misses {
+                    if percent == 0 || weight >= max {
+                        break;
+                    }
+                    weight = weight.saturating_mul(100 + percent as u128) / 100;
+                }
+                (weight.min(max) >> 32) as u64
+            }
+        }
+    }
+}
+
+/// Bad luck protection rules for a single item
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PityRule {
+    pub growth: PityGrowth,
+    /// Guarantees the item after this many misses in a row
+    ///
+    /// Items with zero base weight are never guaranteed
+    pub guarantee_after: Option<u32>,
+}
+
+impl PityRule {
+    pub fn new(growth: PityGrowth, guarantee_after: Option<u32>) -> Self {
+        Self {
+            growth,
+            guarantee_after,
+        }
+    }
+
+    pub fn with_growth(mut self, growth: PityGrowth) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    pub fn with_guarantee_after(mut self, guarantee_after: impl Into<Option<u32>>) -> Self {
+        self.guarantee_after = guarantee_after.into();
+        self
+    }
+}
+
+/// Miss counters of a [PityPool], saved separately from the pool itself
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PityState {
+    misses: Vec<u32>,
+}
+
+impl PityState {
+    /// Number of draws in a row each item was not picked
+    pub fn misses(&self) -> &[u32] {
+        &self.misses
+    }
+}
+
+/// Random pool with bad luck protection, where items get more likely to be
+/// picked the longer they are not
+#[derive(Debug, Clone)]
+pub struct PityPool<Item> {
+    pool: RandomPool<Item>,
+    rules: Vec<PityRule>,
+    state: PityState,
+    /// Buffer for the grown weights, to avoid allocating on every draw
+    weights: Vec<u64>,
+}
+
+impl<Item> PityPool<Item> {
+    /// Creates a pool with the same pity rule for all items
+    pub fn new(pool: RandomPool<Item>, rule: PityRule) -> Self {
+        let rules = vec![rule; pool.len()];
+        Self::with_rules(pool, rules)
+    }
+
+    /// Creates a pool with a separate pity rule for each item
+    ///
+    /// # Panics
+    /// Panics if the number of rules doesn't match the number of items
+    pub fn with_rules(pool: RandomPool<Item>, rules: Vec<PityRule>) -> Self {
+        assert_eq!(
+            pool.len(),
+            rules.len(),
+            "Every item in the pool should have a pity rule"
+        );
+        Self {
+            state: PityState {
+                misses: vec![0; pool.len()],
+            },
+            weights: Vec::with_capacity(pool.len()),
+            pool,
+            rules,
+        }
+    }
+
+    pub fn pool(&self) -> &RandomPool<Item> {
+        &self.pool
+    }
+
+    pub fn rules(&self) -> &[PityRule] {
+        &self.rules
+    }
+
+    pub fn state(&self) -> &PityState {
+        &self.state
+    }
+
+    /// Restores miss counters, like ones loaded from a save file
+    ///
+    /// # Panics
+    /// Panics if the state was made for a pool with a different number of
+    /// items
+    pub fn restore(&mut self, state: PityState) {
+        assert_eq!(
+            state.misses.len(),
+            self.pool.len(),
+            "Pity state should match the pool size"
+        );
+        self.state = state;
+    }
+
+    /// Forgets all misses
+    pub fn reset(&mut self) {
+        self.state.misses.fill(0);
+    }
+
+    /// Returns the current weights of all items, accounting for misses
+    pub fn current_weights(&self) -> Vec<u64> {
+        let mut weights = vec![];
+        self.fill_weights(&mut weights);
+        weights
+    }
+
+    fn fill_weights(&self, weights: &mut Vec<u64>) {
+        // Prevent sum of the weights from overflowing
+        let cap = u64::MAX / self.pool.len().max(1) as u64;
+        weights.clear();
+        weights.extend(
+            self.pool
+                .weights()
+                .iter()
+                .zip(&self.rules)
+                .zip(&self.state.misses)
+                .map(|((&base, rule), &misses)| rule.growth.weight(base, misses).min(cap)),
+        );
+    }
+
+    /// Picks an item, updating miss counters
+    ///
+    /// Items that reached their guarantee are picked first, preferring the
+    /// ones that were missed the most
+    pub fn get(&mut self, state: &mut LuckState) -> &Item {
+        #[cfg(debug_assertions)]
+        if self.pool.is_empty() {
+            panic!("Sampling from empty pool")
+        }
+
+        let guaranteed = self
+            .rules
+            .iter()
+            .zip(&self.state.misses)
+            .enumerate()
+            .filter(|(i, (rule, &misses))| {
+                self.pool.weights()[*i] > 0
+                    && rule
+                        .guarantee_after
+                        .is_some_and(|guarantee| misses >= guarantee)
+            })
+            .max_by_key(|(i, (_, &misses))| (misses, std::cmp::Reverse(*i)))
+            .map(|(i, _)| i);
+
+        let picked = match guaranteed {
+            Some(picked) => picked,
+            None => {
+                let mut weights = std::mem::take(&mut self.weights);
+                self.fill_weights(&mut weights);
+                let picked = chances(state, &weights, None);
+                self.weights = weights;
+                picked
+            }
+        };
+
+        for (i, misses) in self.state.misses.iter_mut().enumerate() {
+            if i == picked {
+                *misses = 0;
+            } else {
+                *misses = misses.saturating_add(1);
+            }
+        }
+
+        &self.pool.items()[picked]
+    }
+}
@@ -1,32 +1,217 @@
-use crate::chances::chances;
+use std::cmp::Ordering;
+
+use crate::alias::AliasTable;
+use crate::chances::chances_filtered;
+use crate::fenwick::FenwickTree;
+use crate::modifier::{modified_draw, LuckModifier, LuckStreak};
+use crate::weight::{validate_weights, WeightError};
 use crate::LuckState;
 
+pub use crate::weight::PoolWeight;
+
+/// Pool of weighted items with constant time draws
+///
+/// Weights are fixed after creation, see [DynamicPool] for pools with
+/// changing weights
 #[derive(Debug, Clone)]
 pub struct RandomPool<Item> {
     choices: Vec<Item>,
     weights: Vec<u64>,
+    table: AliasTable,
 }
 
 impl<Item> RandomPool<Item> {
+    /// # Panics
+    /// Panics if weights are invalid, see [RandomPool::try_new]
     pub fn new(choices: Vec<Item>, weights: Vec<u64>) -> Self {
-        Self { choices, weights }
+        Self::try_new(choices, weights).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates a pool, checking that there is a weight for every item, and
+    /// that the sum of the weights is neither zero nor overflowing
+    pub fn try_new(choices: Vec<Item>, weights: Vec<u64>) -> Result<Self, WeightError> {
+        check_lengths(&choices, &weights)?;
+        validate_weights(&weights)?;
+        let table = AliasTable::new(&weights);
+        Ok(Self {
+            choices,
+            weights,
+            table,
+        })
     }
 
+    /// # Panics
+    /// Panics if weights can't be converted, see
+    /// [RandomPool::try_from_weights]
     pub fn from_weights<Weight: PoolWeight>(
         choices: impl IntoIterator<Item = (Weight, Item)>,
     ) -> Self {
-        let (weights, items) = choices
-            .into_iter()
-            .map(|(w, i)| (w.into_weight(), i))
-            .unzip();
-        Self::new(items, weights)
+        Self::try_from_weights(choices).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates a pool out of weights of any supported type, like floats,
+    /// [crate::weight::Rational] or percentage strings
+    pub fn try_from_weights<Weight: PoolWeight>(
+        choices: impl IntoIterator<Item = (Weight, Item)>,
+    ) -> Result<Self, WeightError> {
+        let (weights, items): (Vec<Weight>, Vec<Item>) = choices.into_iter().unzip();
+        Self::try_new(items, Weight::into_weights(weights)?)
     }
 
     pub fn equal(choices: Vec<Item>) -> Self {
-        Self {
-            weights: vec![1; choices.len()],
+        let weights = vec![1; choices.len()];
+        Self::new(choices, weights)
+    }
+
+    /// Items of the pool, in the same order as [RandomPool::weights]
+    pub fn items(&self) -> &[Item] {
+        &self.choices
+    }
+
+    pub fn weights(&self) -> &[u64] {
+        &self.weights
+    }
+
+    pub fn len(&self) -> usize {
+        self.choices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+
+    pub fn get(&self, state: &mut LuckState) -> &Item {
+        &self.choices[self.get_index(state)]
+    }
+
+    /// Picks an index of an item, same as [RandomPool::get]
+    pub fn get_index(&self, state: &mut LuckState) -> usize {
+        #[cfg(debug_assertions)]
+        if self.choices.is_empty() {
+            panic!("Sampling from empty pool")
+        }
+        self.table.sample(state)
+    }
+
+    /// Picks an item among the ones allowed by the predicate, keeping their
+    /// relative weights
+    ///
+    /// Returns [None] if every item is excluded
+    pub fn get_filtered(
+        &self,
+        state: &mut LuckState,
+        allowed: impl Fn(&Item) -> bool,
+    ) -> Option<&Item> {
+        chances_filtered(state, &self.weights, |i| allowed(&self.choices[i]))
+            .map(|i| &self.choices[i])
+    }
+
+    /// Picks an item with luck modifier applied, see
+    /// [crate::chances::chances_with]
+    ///
+    /// `better` returns [Ordering::Greater] when the first item is more
+    /// favourable
+    pub fn get_with(
+        &self,
+        state: &mut LuckState,
+        modifier: &LuckModifier,
+        streak: Option<&mut LuckStreak>,
+        better: impl Fn(&Item, &Item) -> Ordering,
+    ) -> &Item {
+        #[cfg(debug_assertions)]
+        if self.choices.is_empty() {
+            panic!("Sampling from empty pool")
+        }
+        let picked = modified_draw(
+            state,
+            &self.weights,
+            modifier,
+            streak,
+            |a, b| better(&self.choices[a], &self.choices[b]),
+            |state| self.table.sample(state),
+        );
+        &self.choices[picked]
+    }
+}
+
+/// Pool of weighted items, which weights can be changed after creation
+///
+/// Both draws and weight changes take logarithmic time
+#[derive(Debug, Clone)]
+pub struct DynamicPool<Item> {
+    choices: Vec<Item>,
+    tree: FenwickTree,
+}
+
+impl<Item> DynamicPool<Item> {
+    /// # Panics
+    /// Panics if weights are invalid, see [RandomPool::try_new]
+    pub fn new(choices: Vec<Item>, weights: Vec<u64>) -> Self {
+        Self::try_new(choices, weights).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates a pool, validating weights the same way as
+    /// [RandomPool::try_new]
+    pub fn try_new(choices: Vec<Item>, weights: Vec<u64>) -> Result<Self, WeightError> {
+        check_lengths(&choices, &weights)?;
+        validate_weights(&weights)?;
+        Ok(Self {
+            tree: FenwickTree::new(&weights),
             choices,
+        })
+    }
+
+    /// # Panics
+    /// Panics if weights can't be converted, see
+    /// [RandomPool::try_from_weights]
+    pub fn from_weights<Weight: PoolWeight>(
+        choices: impl IntoIterator<Item = (Weight, Item)>,
+    ) -> Self {
+        Self::try_from_weights(choices).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Same as [RandomPool::try_from_weights]
+    pub fn try_from_weights<Weight: PoolWeight>(
+        choices: impl IntoIterator<Item = (Weight, Item)>,
+    ) -> Result<Self, WeightError> {
+        let (weights, items): (Vec<Weight>, Vec<Item>) = choices.into_iter().unzip();
+        Self::try_new(items, Weight::into_weights(weights)?)
+    }
+
+    pub fn equal(choices: Vec<Item>) -> Self {
+        let weights = vec![1; choices.len()];
+        Self::new(choices, weights)
+    }
+
+    /// Items of the pool, in the same order as [DynamicPool::weights]
+    pub fn items(&self) -> &[Item] {
+        &self.choices
+    }
+
+    pub fn weights(&self) -> &[u64] {
+        self.tree.weights()
+    }
+
+    pub fn len(&self) -> usize {
+        self.choices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+
+    /// Changes the weight of the item at the given index
+    ///
+    /// Returns an error and keeps the old weight if the change would make
+    /// the sum of the weights zero or overflow
+    pub fn set_weight(&mut self, index: usize, weight: u64) -> Result<(), WeightError> {
+        let rest = self.tree.sum() - self.tree.weights()[index];
+        let sum = rest.checked_add(weight).ok_or(WeightError::Overflow)?;
+        if sum == 0 {
+            return Err(WeightError::ZeroSum);
         }
+        self.tree.set(index, weight);
+        Ok(())
     }
 
     pub fn get(&self, state: &mut LuckState) -> &Item {
@@ -34,25 +219,58 @@ impl<Item> RandomPool<Item> {
         if self.choices.is_empty() {
             panic!("Sampling from empty pool")
         }
-        &self.choices[chances(state, &self.weights, None)]
+        &self.choices[self.tree.sample(state)]
     }
-}
 
-pub trait PoolWeight {
-    fn into_weight(self) -> u64;
+    /// Picks an item among the ones allowed by the predicate, keeping their
+    /// relative weights
+    ///
+    /// Returns [None] if every item is excluded
+    pub fn get_filtered(
+        &self,
+        state: &mut LuckState,
+        allowed: impl Fn(&Item) -> bool,
+    ) -> Option<&Item> {
+        chances_filtered(state, self.tree.weights(), |i| allowed(&self.choices[i]))
+            .map(|i| &self.choices[i])
+    }
+
+    /// Picks an item with luck modifier applied, see [RandomPool::get_with]
+    pub fn get_with(
+        &self,
+        state: &mut LuckState,
+        modifier: &LuckModifier,
+        streak: Option<&mut LuckStreak>,
+        better: impl Fn(&Item, &Item) -> Ordering,
+    ) -> &Item {
+        #[cfg(debug_assertions)]
+        if self.choices.is_empty() {
+            panic!("Sampling from empty pool")
+        }
+        let picked = modified_draw(
+            state,
+            self.tree.weights(),
+            modifier,
+            streak,
+            |a, b| better(&self.choices[a], &self.choices[b]),
+            |state| self.tree.sample(state),
+        );
+        &self.choices[picked]
+    }
 }
 
-impl PoolWeight for u64 {
-    fn into_weight(self) -> u64 {
-        self
+impl<Item> From<RandomPool<Item>> for DynamicPool<Item> {
+    fn from(pool: RandomPool<Item>) -> Self {
+        Self::new(pool.choices, pool.weights)
     }
 }
 
-impl PoolWeight for f64 {
-    fn into_weight(self) -> u64 {
-        // Cut off at a sane point
-        debug_assert!(self < 1e9, "Weights are too high");
-        // Good enough
-        (self * 1e6).round() as u64
+fn check_lengths<Item>(choices: &[Item], weights: &[u64]) -> Result<(), WeightError> {
+    if choices.len() != weights.len() {
+        return Err(WeightError::LengthMismatch {
+            items: choices.len(),
+            weights: weights.len(),
+        });
     }
+    Ok(())
 }
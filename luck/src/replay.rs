@@ -0,0 +1,201 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::chances::chances;
+use crate::pool::RandomPool;
+use crate::LuckState;
+
+/// Single random decision made during recording
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawRecord {
+    /// Call-site label, naming the system that made the draw
+    pub label: String,
+    /// Range the result was drawn from
+    pub range: Range<u64>,
+    pub result: u64,
+}
+
+/// Playback went differently from the recording
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplayError {
+    /// Draw was made by a different system than during recording
+    LabelMismatch {
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    /// Same system drew from a different range, like a pool of a
+    /// different size
+    RangeMismatch {
+        index: usize,
+        label: String,
+        expected: Range<u64>,
+        found: Range<u64>,
+    },
+    /// More draws were made than recorded
+    Exhausted { index: usize, label: String },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::LabelMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Replay diverged at draw #{index}: expected a draw from `{expected}`, but `{found}` drew instead"
+            ),
+            ReplayError::RangeMismatch {
+                index,
+                label,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Replay diverged at draw #{index} from `{label}`: expected range {expected:?}, got {found:?}"
+            ),
+            ReplayError::Exhausted { index, label } => write!(
+                f,
+                "Replay diverged at draw #{index} from `{label}`: recording has no more draws"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Debug, Clone)]
+enum ReplayMode {
+    Recording(LuckState),
+    Playback { cursor: usize },
+}
+
+/// Wrapper around [LuckState] that records every draw, or feeds recorded
+/// results back during playback
+///
+/// Recording never fails, while playback checks that each draw comes from
+/// the same call site and range as during recording
+#[derive(Debug, Clone)]
+pub struct ReplayLuck {
+    mode: ReplayMode,
+    records: Vec<DrawRecord>,
+}
+
+impl ReplayLuck {
+    /// Starts recording draws made from the state
+    pub fn record(state: LuckState) -> Self {
+        Self {
+            mode: ReplayMode::Recording(state),
+            records: vec![],
+        }
+    }
+
+    /// Replays previously recorded draws
+    pub fn playback(records: Vec<DrawRecord>) -> Self {
+        Self {
+            mode: ReplayMode::Playback { cursor: 0 },
+            records,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, ReplayMode::Recording(_))
+    }
+
+    pub fn records(&self) -> &[DrawRecord] {
+        &self.records
+    }
+
+    pub fn into_records(self) -> Vec<DrawRecord> {
+        self.records
+    }
+
+    /// Checks whenever all recorded draws were played back. Always `true`
+    /// while recording
+    pub fn is_finished(&self) -> bool {
+        match self.mode {
+            ReplayMode::Recording(_) => true,
+            ReplayMode::Playback { cursor } => cursor >= self.records.len(),
+        }
+    }
+
+    /// Draws a number from the range
+    pub fn gen_range(&mut self, label: &str, range: Range<u64>) -> Result<u64, ReplayError> {
+        self.draw(label, range.clone(), |state| state.state.gen_range(range))
+    }
+
+    /// Recorded counterpart of [chances]
+    pub fn chances(&mut self, label: &str, weights: &[u64]) -> Result<usize, ReplayError> {
+        self.draw(label, 0..weights.len() as u64, |state| {
+            chances(state, weights, None) as u64
+        })
+        .map(|i| i as usize)
+    }
+
+    /// Recorded counterpart of [RandomPool::get]
+    pub fn get<'a, Item>(
+        &mut self,
+        label: &str,
+        pool: &'a RandomPool<Item>,
+    ) -> Result<&'a Item, ReplayError> {
+        let index = self.draw(label, 0..pool.len() as u64, |state| {
+            pool.get_index(state) as u64
+        })?;
+        Ok(&pool.items()[index as usize])
+    }
+
+    /// Records the result of `draw`, or returns the recorded one during
+    /// playback
+    ///
+    /// `range` must cover all possible results of `draw`
+    pub fn draw(
+        &mut self,
+        label: &str,
+        range: Range<u64>,
+        draw: impl FnOnce(&mut LuckState) -> u64,
+    ) -> Result<u64, ReplayError> {
+        match &mut self.mode {
+            ReplayMode::Recording(state) => {
+                let result = draw(state);
+                debug_assert!(range.contains(&result), "Draw result is out of range");
+                self.records.push(DrawRecord {
+                    label: label.to_string(),
+                    range,
+                    result,
+                });
+                Ok(result)
+            }
+            ReplayMode::Playback { cursor } => {
+                let index = *cursor;
+                let Some(record) = self.records.get(index) else {
+                    return Err(ReplayError::Exhausted {
+                        index,
+                        label: label.to_string(),
+                    });
+                };
+                if record.label != label {
+                    return Err(ReplayError::LabelMismatch {
+                        index,
+                        expected: record.label.clone(),
+                        found: label.to_string(),
+                    });
+                }
+                if record.range != range {
+                    return Err(ReplayError::RangeMismatch {
+                        index,
+                        label: label.to_string(),
+                        expected: record.range.clone(),
+                        found: range,
+                    });
+                }
+                *cursor += 1;
+                Ok(record.result)
+            }
+        }
+    }
+}
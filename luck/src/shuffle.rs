@@ -0,0 +1,177 @@
+use rand::Rng;
+
+use crate::pool::RandomPool;
+use crate::weight::gcd;
+use crate::LuckState;
+
+/// Default limit on the number of items in a full bag, see
+/// [ShuffleBag::with_max_size]
+pub const DEFAULT_MAX_BAG_SIZE: u64 = 1000;
+
+/// Deals items of a pool without replacement, like cards from a deck
+///
+/// The bag is filled with copies of each item proportionally to its weight
+/// and refilled once it runs out, so over a full bag every item appears
+/// exactly as often as its weight says, without long streaks. Weights that
+/// would need a huge bag are rounded, see [ShuffleBag::with_max_size]
+#[derive(Debug, Clone)]
+pub struct ShuffleBag<Item> {
+    pool: RandomPool<Item>,
+    /// Number of copies of each item in a full bag
+    copies: Vec<u64>,
+    multiplier: u64,
+    max_size: u64,
+    no_repeat: bool,
+    /// Indices of items left in the bag
+    bag: Vec<usize>,
+    last: Option<usize>,
+}
+
+impl<Item> ShuffleBag<Item> {
+    /// Creates a bag holding the pool weights reduced by their greatest
+    /// common divisor, so weights of `[2, 4]` yield a bag of three items
+    ///
+    /// Bags that would still hold more than [DEFAULT_MAX_BAG_SIZE] items get
+    /// scaled down, see [ShuffleBag::with_max_size]
+    pub fn new(pool: RandomPool<Item>) -> Self {
+        let copies = bag_copies(pool.weights(), 1, DEFAULT_MAX_BAG_SIZE);
+        Self {
+            pool,
+            copies,
+            multiplier: 1,
+            max_size: DEFAULT_MAX_BAG_SIZE,
+            no_repeat: false,
+            bag: vec![],
+            last: None,
+        }
+    }
+
+    /// Makes the bag hold `multiplier` times more copies of each item, making
+    /// the sequence less predictable while still limiting streaks
+    ///
+    /// The bag still holds at most the max size, see
+    /// [ShuffleBag::with_max_size]. Empties the bag, so the next draw starts
+    /// a new one
+    pub fn with_multiplier(mut self, multiplier: u64) -> Self {
+        self.multiplier = multiplier.max(1);
+        self.update_copies();
+        self
+    }
+
+    /// Limits the number of items in a full bag, including the multiplier
+    ///
+    /// Weights that don't fit get scaled down to exactly `max_size` copies,
+    /// rounded by largest remainders, so items with tiny weights may get no
+    /// copies at all. Empties the bag, so the next draw starts a new one
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size.max(1);
+        self.update_copies();
+        self
+    }
+
+    /// Prevents dealing the same item twice in a row, unless the bag holds
+    /// nothing else
+    pub fn with_no_repeat(mut self, no_repeat: bool) -> Self {
+        self.no_repeat = no_repeat;
+        self
+    }
+
+    pub fn pool(&self) -> &RandomPool<Item> {
+        &self.pool
+    }
+
+    /// Number of items in a full bag
+    pub fn bag_size(&self) -> u64 {
+        self.copies.iter().sum()
+    }
+
+    /// Number of items left before the bag gets refilled
+    pub fn remaining(&self) -> usize {
+        self.bag.len()
+    }
+
+    /// Discards the rest of the current bag, so the next draw starts a new
+    /// one
+    pub fn reset(&mut self) {
+        self.bag.clear();
+        self.last = None;
+    }
+
+    /// Deals the next item, refilling the bag if it's empty
+    pub fn get(&mut self, state: &mut LuckState) -> &Item {
+        if self.bag.is_empty() {
+            self.refill();
+        }
+
+        #[cfg(debug_assertions)]
+        if self.bag.is_empty() {
+            panic!("Sampling from empty bag")
+        }
+
+        let repeats = match self.last {
+            Some(last) if self.no_repeat => self.bag.iter().filter(|&&i| i == last).count(),
+            _ => 0,
+        };
+
+        let slot = if repeats == 0 || repeats == self.bag.len() {
+            state.state.gen_range(0..self.bag.len())
+        } else {
+            // Pick among the other items only
+            let last = self.last;
+            let nth = state.state.gen_range(0..self.bag.len() - repeats);
+            self.bag
+                .iter()
+                .enumerate()
+                .filter(|(_, &i)| Some(i) != last)
+                .nth(nth)
+                .map(|(slot, _)| slot)
+                .expect("Should have enough non-repeating items")
+        };
+
+        let picked = self.bag.swap_remove(slot);
+        self.last = Some(picked);
+        &self.pool.items()[picked]
+    }
+
+    fn update_copies(&mut self) {
+        self.copies = bag_copies(self.pool.weights(), self.multiplier, self.max_size);
+        self.bag.clear();
+    }
+
+    fn refill(&mut self) {
+        for (i, &copies) in self.copies.iter().enumerate() {
+            for _ in 0..copies {
+                self.bag.push(i);
+            }
+        }
+    }
+}
+
+/// Number of copies of each item in a bag of at most `max_size` items, with
+/// reduced weights repeated `multiplier` times
+fn bag_copies(weights: &[u64], multiplier: u64, max_size: u64) -> Vec<u64> {
+    let divisor = weights.iter().copied().fold(0, gcd).max(1);
+    let copies: Vec<u64> = weights.iter().map(|w| w / divisor).collect();
+    let sum = copies.iter().map(|&c| c as u128).sum::<u128>();
+    if sum * multiplier as u128 <= max_size as u128 {
+        return copies.iter().map(|&c| c * multiplier).collect();
+    }
+
+    // Multiplier cancels out once the bag gets scaled down to `max_size`
+
+    // Largest remainder method, ties go to the earlier items
+    let (mut scaled, remainders): (Vec<u64>, Vec<u128>) = copies
+        .iter()
+        .map(|&c| {
+            let quota = c as u128 * max_size as u128;
+            ((quota / sum) as u64, quota % sum)
+        })
+        .unzip();
+    let missing = max_size - scaled.iter().sum::<u64>();
+    let mut order: Vec<usize> = (0..scaled.len()).collect();
+    order.sort_by(|&a, &b| remainders[b].cmp(&remainders[a]));
+    for &i in order.iter().take(missing as usize) {
+        scaled[i] += 1;
+    }
+    scaled
+}
@@ -0,0 +1,140 @@
+//! Statistical helpers for checking that draws follow the intended
+//! distribution, for use in tests
+//!
+//! Draws from a seeded [crate::LuckState] are deterministic, so a check that
+//! passes once keeps passing until the sampling logic changes
+
+/// Probability of a correct distribution failing the check
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Significance {
+    /// 5%
+    Loose,
+    /// 1%
+    Normal,
+    /// 0.1%
+    Strict,
+}
+
+impl Significance {
+    /// Upper quantile of the standard normal distribution
+    fn normal_quantile(self) -> f64 {
+        match self {
+            Significance::Loose => 1.6449,
+            Significance::Normal => 2.3263,
+            Significance::Strict => 3.0902,
+        }
+    }
+
+    /// Coefficient of the Kolmogorov-Smirnov critical value
+    fn ks_coefficient(self) -> f64 {
+        match self {
+            Significance::Loose => 1.358,
+            Significance::Normal => 1.628,
+            Significance::Strict => 1.949,
+        }
+    }
+}
+
+/// Counts how many times each index was drawn
+///
+/// # Panics
+/// Panics if `draw` returns an index outside of `0..len`
+pub fn observe(len: usize, draws: usize, mut draw: impl FnMut() -> usize) -> Vec<u64> {
+    let mut counts = vec![0; len];
+    for _ in 0..draws {
+        counts[draw()] += 1;
+    }
+    counts
+}
+
+/// Expected fraction of draws of each index
+pub fn expected_frequencies(weights: &[u64]) -> Vec<f64> {
+    let sum = weights.iter().sum::<u64>() as f64;
+    weights.iter().map(|&w| w as f64 / sum).collect()
+}
+
+/// Pearson's chi-square statistic of the observed counts against the
+/// weights
+///
+/// Returns infinity if an index with zero weight was ever drawn
+pub fn chi_square(observed: &[u64], weights: &[u64]) -> f64 {
+    let total = observed.iter().sum::<u64>() as f64;
+    observed
+        .iter()
+        .zip(expected_frequencies(weights))
+        .map(|(&count, frequency)| {
+            let expected = frequency * total;
+            if expected == 0.0 {
+                if count == 0 {
+                    0.0
+                } else {
+                    f64::INFINITY
+                }
+            } else {
+                let diff = count as f64 - expected;
+                diff * diff / expected
+            }
+        })
+        .sum()
+}
+
+/// Critical value of the chi-square distribution, using the Wilson-Hilferty
+/// approximation
+pub fn chi_square_critical(degrees_of_freedom: usize, significance: Significance) -> f64 {
+    let k = degrees_of_freedom.max(1) as f64;
+    let spread = 2.0 / (9.0 * k);
+    let base = 1.0 - spread + significance.normal_quantile() * spread.sqrt();
+    k * base * base * base
+}
+
+/// Kolmogorov-Smirnov statistic, the largest difference between the
+/// observed and the expected cumulative frequencies
+pub fn ks_statistic(observed: &[u64], weights: &[u64]) -> f64 {
+    let total = observed.iter().sum::<u64>() as f64;
+    let mut observed_cumulative = 0.0;
+    let mut expected_cumulative = 0.0;
+    let mut largest: f64 = 0.0;
+    for (&count, frequency) in observed.iter().zip(expected_frequencies(weights)) {
+        observed_cumulative += count as f64 / total;
+        expected_cumulative += frequency;
+        largest = largest.max((observed_cumulative - expected_cumulative).abs());
+    }
+    largest
+}
+
+/// Critical value of the Kolmogorov-Smirnov statistic for the given number
+/// of draws
+///
+/// The test is conservative for discrete distributions
+pub fn ks_critical(draws: u64, significance: Significance) -> f64 {
+    significance.ks_coefficient() / (draws as f64).sqrt()
+}
+
+/// Checks the observed counts against the weights with both chi-square and
+/// Kolmogorov-Smirnov tests
+///
+/// # Panics
+/// Panics with both statistics if either of the tests fails
+pub fn assert_distribution(observed: &[u64], weights: &[u64], significance: Significance) {
+    assert_eq!(
+        observed.len(),
+        weights.len(),
+        "Every observed index should have a weight"
+    );
+    let draws = observed.iter().sum::<u64>();
+    let degrees = weights.iter().filter(|&&w| w > 0).count().saturating_sub(1);
+
+    let chi = chi_square(observed, weights);
+    let chi_limit = chi_square_critical(degrees, significance);
+    let ks = ks_statistic(observed, weights);
+    let ks_limit = ks_critical(draws, significance);
+
+    assert!(
+        chi <= chi_limit && ks <= ks_limit,
+        "Draws don't match the weights at {significance:?} significance\n\
+         observed: {observed:?}\n\
+         weights:  {weights:?}\n\
+         chi-square: {chi:.3} (limit {chi_limit:.3})\n\
+         KS: {ks:.5} (limit {ks_limit:.5})"
+    );
+}
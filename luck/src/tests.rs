@@ -0,0 +1,628 @@
+use crate::alias::AliasTable;
+use crate::chances::{chances, chances_filtered, chances_with};
+use crate::fenwick::FenwickTree;
+use crate::modifier::{LuckModifier, LuckStreak};
+use crate::pity::{PityGrowth, PityPool, PityRule};
+use crate::pool::{DynamicPool, RandomPool};
+use crate::replay::{ReplayError, ReplayLuck};
+use crate::shuffle::ShuffleBag;
+use crate::stats::{assert_distribution, observe, Significance};
+use crate::weight::{FixedWeight, Rational, WeightError};
+use crate::LuckState;
+
+fn rolls(state: &mut LuckState, count: usize) -> Vec<usize> {
+    (0..count)
+        .map(|_| chances(state, &[1, 2, 3, 4], None))
+        .collect()
+}
+
+#[test]
+fn clone_continues_the_same_sequence() {
+    let mut state = LuckState::new(42);
+    rolls(&mut state, 10);
+    let mut cloned = state.clone();
+    assert_eq!(rolls(&mut state, 100), rolls(&mut cloned, 100));
+}
+
+#[test]
+fn forks_are_deterministic_and_independent() {
+    let mut parent = LuckState::new(42);
+    let mut board = parent.fork("board");
+    let expected = rolls(&mut board.clone(), 100);
+
+    // Rolling the parent or other forks doesn't affect the fork
+    rolls(&mut parent, 10);
+    rolls(&mut parent.fork("enemy"), 10);
+    assert_eq!(rolls(&mut parent.fork("board"), 100), expected);
+    assert_eq!(rolls(&mut board, 100), expected);
+
+    assert_ne!(rolls(&mut parent.fork("loot"), 100), expected);
+    assert_ne!(rolls(&mut LuckState::new(43).fork("board"), 100), expected);
+}
+
+#[test]
+fn serialization_round_trip() {
+    let mut state = LuckState::new(42);
+    rolls(&mut state, 10);
+    let saved = serde_json::to_string(&state).unwrap();
+    let mut restored: LuckState = serde_json::from_str(&saved).unwrap();
+    assert_eq!(restored.seed(), state.seed());
+    assert_eq!(rolls(&mut restored, 100), rolls(&mut state, 100));
+}
+
+/// Pool with a rare item at index 0, dropping 1% of the time without pity
+fn rare_pool() -> RandomPool<usize> {
+    RandomPool::new(vec![0, 1], vec![1, 99])
+}
+
+/// Returns the frequency of the rare item and the longest streak without it
+fn rare_stats(mut draw: impl FnMut() -> usize, draws: usize) -> (f64, usize) {
+    let mut hits = 0;
+    let mut streak = 0;
+    let mut longest = 0;
+    for _ in 0..draws {
+        if draw() == 0 {
+            hits += 1;
+            streak = 0;
+        } else {
+            streak += 1;
+            longest = longest.max(streak);
+        }
+    }
+    (hits as f64 / draws as f64, longest)
+}
+
+#[test]
+fn pity_guarantee_limits_streaks() {
+    let mut state = LuckState::new(42);
+    let mut pool = PityPool::with_rules(
+        rare_pool(),
+        vec![
+            PityRule::default().with_guarantee_after(10),
+            PityRule::default(),
+        ],
+    );
+    let (frequency, longest) = rare_stats(|| *pool.get(&mut state), 100_000);
+    assert!(longest <= 10, "Longest streak was {longest}");
+    // Guarantee alone yields one rare item every 11 draws, plus natural hits
+    assert!(frequency > 1.0 / 11.0, "Frequency was {frequency}");
+    assert!(frequency < 0.11, "Frequency was {frequency}");
+}
+
+#[test]
+fn pity_growth_increases_frequency() {
+    let draws = 100_000;
+    let mut state = LuckState::new(42);
+    let pool = rare_pool();
+    let (base, base_longest) = rare_stats(|| *pool.get(&mut state), draws);
+    assert!((base - 0.01).abs() < 0.002, "Base frequency was {base}");
+
+    for growth in [
+        PityGrowth::Linear { step: 5 },
+        PityGrowth::Exponential { percent: 50 },
+    ] {
+        let mut pool = PityPool::new(rare_pool(), PityRule::default().with_growth(growth));
+        let (frequency, longest) = rare_stats(|| *pool.get(&mut state), draws);
+        assert!(
+            frequency > base * 3.0,
+            "{growth:?} frequency was {frequency}"
+        );
+        assert!(
+            longest < base_longest,
+            "{growth:?} longest streak was {longest}"
+        );
+    }
+}
+
+#[test]
+fn pity_counters_reset_on_pick() {
+    let mut state = LuckState::new(42);
+    let mut pool = PityPool::new(
+        RandomPool::new(vec!['a', 'b'], vec![1, 0]),
+        PityRule::default().with_growth(PityGrowth::Linear { step: 1 }),
+    );
+    assert_eq!(pool.current_weights(), vec![1, 0]);
+    assert_eq!(*pool.get(&mut state), 'a');
+    assert_eq!(*pool.get(&mut state), 'a');
+    assert_eq!(pool.state().misses(), &[0, 2]);
+    // Zero weight items don't grow
+    assert_eq!(pool.current_weights(), vec![1, 0]);
+
+    let saved = serde_json::to_string(pool.state()).unwrap();
+    pool.reset();
+    assert_eq!(pool.state().misses(), &[0, 0]);
+    pool.restore(serde_json::from_str(&saved).unwrap());
+    assert_eq!(pool.state().misses(), &[0, 2]);
+}
+
+#[test]
+fn pity_growth_curves() {
+    assert_eq!(PityGrowth::None.weight(10, 5), 10);
+    assert_eq!(PityGrowth::Linear { step: 3 }.weight(10, 5), 25);
+    assert_eq!(PityGrowth::Exponential { percent: 100 }.weight(10, 3), 80);
+    assert_eq!(
+        PityGrowth::Linear { step: u64::MAX }.weight(10, 5),
+        u64::MAX
+    );
+    assert_eq!(
+        PityGrowth::Exponential { percent: 100 }.weight(10, 1000),
+        u64::MAX
+    );
+}
+
+#[test]
+fn pity_never_picks_zero_weights() {
+    for growth in [
+        PityGrowth::Linear { step: 1 },
+        PityGrowth::Exponential { percent: 100 },
+    ] {
+        assert_eq!(growth.weight(0, 5), 0);
+        // Returns right away instead of looping over all misses
+        assert_eq!(growth.weight(0, u32::MAX), 0);
+    }
+
+    let mut state = LuckState::new(42);
+    let mut pool = PityPool::new(
+        RandomPool::new(vec!['a', 'b'], vec![1, 0]),
+        PityRule::default()
+            .with_growth(PityGrowth::Linear { step: 1 })
+            .with_guarantee_after(3),
+    );
+    for _ in 0..100 {
+        assert_eq!(*pool.get(&mut state), 'a');
+    }
+    assert_eq!(pool.state().misses(), &[0, 100]);
+}
+
+#[test]
+fn shuffle_bag_deals_exact_proportions() {
+    let mut state = LuckState::new(42);
+    let mut bag = ShuffleBag::new(RandomPool::new(vec!['a', 'b', 'c'], vec![2, 4, 6]));
+    assert_eq!(bag.bag_size(), 6);
+    for _ in 0..100 {
+        let mut dealt: Vec<char> = (0..6).map(|_| *bag.get(&mut state)).collect();
+        dealt.sort();
+        assert_eq!(dealt, vec!['a', 'b', 'b', 'c', 'c', 'c']);
+        assert_eq!(bag.remaining(), 0);
+    }
+}
+
+#[test]
+fn shuffle_bag_multiplier() {
+    let mut state = LuckState::new(42);
+    let mut bag = ShuffleBag::new(RandomPool::new(vec!['a', 'b'], vec![1, 3])).with_multiplier(3);
+    assert_eq!(bag.bag_size(), 12);
+    let mut dealt: Vec<char> = (0..12).map(|_| *bag.get(&mut state)).collect();
+    dealt.sort();
+    assert_eq!(dealt.iter().filter(|&&c| c == 'a').count(), 3);
+    assert_eq!(bag.remaining(), 0);
+
+    // Multiplier doesn't grow the bag past its max size
+    let pool = RandomPool::new(vec!['a', 'b'], vec![1, 3]);
+    let mut bag = ShuffleBag::new(pool.clone()).with_multiplier(u64::MAX);
+    assert_eq!(bag.bag_size(), 1000);
+    let dealt: Vec<char> = (0..1000).map(|_| *bag.get(&mut state)).collect();
+    assert_eq!(dealt.iter().filter(|&&c| c == 'a').count(), 250);
+    assert_eq!(bag.remaining(), 0);
+
+    let bag = ShuffleBag::new(pool).with_multiplier(3).with_max_size(10);
+    assert_eq!(bag.bag_size(), 10);
+}
+
+#[test]
+fn shuffle_bag_float_weights() {
+    let mut state = LuckState::new(42);
+    let pool = RandomPool::from_weights([(0.1, 'a'), (0.2, 'b'), (0.7, 'c')]);
+    let mut bag = ShuffleBag::new(pool.clone());
+    assert_eq!(bag.bag_size(), 1000);
+    let dealt: Vec<char> = (0..1000).map(|_| *bag.get(&mut state)).collect();
+    assert_eq!(dealt.iter().filter(|&&c| c == 'a').count(), 100);
+    assert_eq!(dealt.iter().filter(|&&c| c == 'b').count(), 200);
+    assert_eq!(bag.remaining(), 0);
+
+    // Leftover copies go to the largest remainders
+    let mut bag = ShuffleBag::new(RandomPool::new(vec!['a', 'b', 'c'], vec![1, 1, 2]))
+        .with_max_size(6)
+        .with_multiplier(2);
+    assert_eq!(bag.bag_size(), 6);
+    let mut dealt: Vec<char> = (0..6).map(|_| *bag.get(&mut state)).collect();
+    dealt.sort();
+    assert_eq!(dealt, vec!['a', 'a', 'b', 'c', 'c', 'c']);
+
+    let mut bag = ShuffleBag::new(pool).with_max_size(1);
+    assert_eq!(bag.bag_size(), 1);
+    assert_eq!(*bag.get(&mut state), 'c');
+}
+
+#[test]
+fn shuffle_bag_no_repeat() {
+    let mut state = LuckState::new(42);
+    let mut bag = ShuffleBag::new(RandomPool::equal(vec![0, 1, 2, 3])).with_no_repeat(true);
+    let dealt: Vec<i32> = (0..10_000).map(|_| *bag.get(&mut state)).collect();
+    // Including the border between bags
+    assert!(dealt.windows(2).all(|w| w[0] != w[1]));
+
+    // Single item bags have no choice but to repeat
+    let mut bag = ShuffleBag::new(RandomPool::equal(vec!['a'])).with_no_repeat(true);
+    assert_eq!(*bag.get(&mut state), 'a');
+    assert_eq!(*bag.get(&mut state), 'a');
+}
+
+#[test]
+fn alias_table_matches_weights() {
+    let mut state = LuckState::new(42);
+    for weights in [
+        vec![1, 2, 3, 4],
+        vec![0, 5, 0, 5],
+        vec![1000, 1, 1, 1, 1, 1],
+        vec![7],
+    ] {
+        let table = AliasTable::new(&weights);
+        assert_eq!(table.sum(), weights.iter().sum::<u64>());
+        let observed = observe(weights.len(), 100_000, || table.sample(&mut state));
+        assert_distribution(&observed, &weights, Significance::Strict);
+    }
+}
+
+#[test]
+fn alias_table_never_picks_zero_weights() {
+    let mut state = LuckState::new(42);
+    let table = AliasTable::new(&[0, 3, 0, 0, 1, 0]);
+    for _ in 0..10_000 {
+        let picked = table.sample(&mut state);
+        assert!(picked == 1 || picked == 4, "Picked {picked}");
+    }
+}
+
+#[test]
+fn fenwick_tree_updates() {
+    let mut tree = FenwickTree::new(&[1, 2, 3, 4, 5]);
+    assert_eq!(tree.sum(), 15);
+    assert_eq!(tree.prefix_sum(3), 6);
+    let found: Vec<usize> = (0..15).map(|v| tree.find(v)).collect();
+    assert_eq!(found, vec![0, 1, 1, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4]);
+
+    tree.set(1, 0);
+    tree.set(4, 1);
+    assert_eq!(tree.weights(), &[1, 0, 3, 4, 1]);
+    assert_eq!(tree.sum(), 9);
+    let found: Vec<usize> = (0..9).map(|v| tree.find(v)).collect();
+    assert_eq!(found, vec![0, 2, 2, 2, 3, 3, 3, 3, 4]);
+}
+
+#[test]
+fn dynamic_pool_follows_weight_changes() {
+    let mut state = LuckState::new(42);
+    let mut pool = DynamicPool::from(RandomPool::new(vec![0, 1, 2], vec![1, 1, 1]));
+    let observed = observe(3, 100_000, || *pool.get(&mut state));
+    assert_distribution(&observed, &[1, 1, 1], Significance::Strict);
+
+    pool.set_weight(0, 0).unwrap();
+    pool.set_weight(2, 3).unwrap();
+    let observed = observe(3, 100_000, || *pool.get(&mut state));
+    assert_distribution(&observed, &[0, 1, 3], Significance::Strict);
+}
+
+/// Average outcome of a pool of `0..10` where higher is better
+fn average_roll(modifier: LuckModifier, streak: Option<&mut LuckStreak>) -> f64 {
+    let mut state = LuckState::new(42);
+    let pool = RandomPool::equal((0..10).collect());
+    let mut streak = streak;
+    let draws = 10_000;
+    let total: i32 = (0..draws)
+        .map(|_| *pool.get_with(&mut state, &modifier, streak.as_deref_mut(), Ord::cmp))
+        .sum();
+    total as f64 / draws as f64
+}
+
+#[test]
+fn luck_modifiers_shift_average() {
+    let neutral = average_roll(LuckModifier::new(), None);
+    assert!((neutral - 4.5).abs() < 0.1, "Neutral average was {neutral}");
+
+    let advantage = average_roll(LuckModifier::advantage(1), None);
+    let disadvantage = average_roll(LuckModifier::disadvantage(1), None);
+    // Best of two rolls of 0..10 averages at 6.15
+    assert!((advantage - 6.15).abs() < 0.1, "Advantage was {advantage}");
+    assert!(
+        (disadvantage - 2.85).abs() < 0.1,
+        "Disadvantage was {disadvantage}"
+    );
+
+    let lucky = average_roll(LuckModifier::new().with_luck(50), None);
+    let unlucky = average_roll(LuckModifier::new().with_luck(-50), None);
+    assert!(lucky > neutral + 0.5, "Lucky average was {lucky}");
+    assert!(unlucky < neutral - 0.5, "Unlucky average was {unlucky}");
+}
+
+#[test]
+fn luck_modifiers_compose() {
+    let ring = LuckModifier::new().with_luck(10);
+    let curse = LuckModifier::disadvantage(2).with_streak_limit(5);
+    let charm = LuckModifier::advantage(1)
+        .with_luck(15)
+        .with_streak_limit(3);
+    let total: LuckModifier = [ring, curse, charm].into_iter().sum();
+    assert_eq!(
+        total,
+        LuckModifier::new()
+            .with_advantage(-1)
+            .with_luck(25)
+            .with_streak_limit(3)
+    );
+    assert!((ring + LuckModifier::new().with_luck(-10)).is_neutral());
+}
+
+#[test]
+fn luck_shifts_weights_by_rank() {
+    let mut state = LuckState::new(42);
+    let weights = [100, 100, 100];
+    let observed = observe(3, 100_000, || {
+        let modifier = LuckModifier::new().with_luck(50);
+        chances_with(&mut state, &weights, None, &modifier, None, |a, b| {
+            a.cmp(&b)
+        })
+    });
+    // Worst outcome loses half of its weight, best one gains half
+    assert_distribution(&observed, &[50, 100, 150], Significance::Strict);
+}
+
+#[test]
+fn luck_never_removes_all_outcomes() {
+    let mut state = LuckState::new(42);
+    // Only the best outcome is possible, and bad luck takes its weight away
+    let weights = [0, 5];
+    for luck in [-100, -1000, i32::MIN] {
+        let modifier = LuckModifier::new().with_luck(luck);
+        for _ in 0..100 {
+            let picked = chances_with(&mut state, &weights, None, &modifier, None, |a, b| {
+                a.cmp(&b)
+            });
+            assert_eq!(picked, 1);
+        }
+    }
+}
+
+#[test]
+fn streak_limit_clamps_bad_streaks() {
+    let mut state = LuckState::new(42);
+    // Outcomes below the middle one are bad, and very likely
+    let weights = [5, 5, 5, 5, 5, 1, 0, 0, 0, 0, 1];
+    let modifier = LuckModifier::new().with_streak_limit(3);
+    let mut streak = LuckStreak::default();
+    let mut longest = 0;
+    let mut good = 0;
+    for _ in 0..10_000 {
+        let picked = chances_with(
+            &mut state,
+            &weights,
+            None,
+            &modifier,
+            Some(&mut streak),
+            |a, b| a.cmp(&b),
+        );
+        if picked >= 5 {
+            good += 1;
+        }
+        longest = longest.max(streak.bad_draws());
+    }
+    assert_eq!(longest, 3);
+    // At least every fourth draw is good
+    assert!(good >= 2_500, "Got {good} good draws");
+}
+
+#[test]
+fn filtered_draws_keep_relative_weights() {
+    let mut state = LuckState::new(42);
+    let pool = RandomPool::new(vec![0, 1, 2, 3], vec![1, 2, 3, 4]);
+    let observed = observe(4, 100_000, || {
+        *pool.get_filtered(&mut state, |&i| i != 2).unwrap()
+    });
+    assert_distribution(&observed, &[1, 2, 0, 4], Significance::Strict);
+
+    let pool = DynamicPool::from(pool);
+    let observed = observe(4, 100_000, || {
+        *pool.get_filtered(&mut state, |&i| i % 2 == 1).unwrap()
+    });
+    assert_distribution(&observed, &[0, 2, 0, 4], Significance::Strict);
+}
+
+#[test]
+fn filtered_draws_fail_when_everything_is_excluded() {
+    let mut state = LuckState::new(42);
+    let pool = RandomPool::new(vec!['a', 'b', 'c'], vec![1, 0, 1]);
+    assert_eq!(pool.get_filtered(&mut state, |_| false), None);
+    // Allowed items with zero weight can't be picked either
+    assert_eq!(pool.get_filtered(&mut state, |&c| c == 'b'), None);
+    assert_eq!(chances_filtered(&mut state, &[], |_| true), None);
+    assert_eq!(chances_filtered(&mut state, &[0, 5], |i| i == 1), Some(1));
+}
+
+#[test]
+fn chances_match_weights() {
+    let mut state = LuckState::new(42);
+    for weights in [vec![1, 2, 3, 4], vec![1, 0, 1], vec![1_000_000, 1]] {
+        let observed = observe(weights.len(), 100_000, || {
+            chances(&mut state, &weights, None)
+        });
+        assert_distribution(&observed, &weights, Significance::Strict);
+    }
+}
+
+#[test]
+fn float_weights_keep_proportions() {
+    let mut state = LuckState::new(42);
+    let pool = RandomPool::from_weights([(0.25, 0), (1.5, 1), (0.001, 2), (3.0, 3)]);
+    // Weights are scaled by a power of two, keeping their sum below 2^62
+    let scale = 1u64 << 59;
+    assert_eq!(
+        pool.weights(),
+        &[scale / 4, scale + scale / 2, 576460752303424, scale * 3]
+    );
+    let observed = observe(4, 100_000, || *pool.get(&mut state));
+    assert_distribution(&observed, pool.weights(), Significance::Strict);
+}
+
+#[test]
+#[should_panic(expected = "Draws don't match the weights")]
+fn distribution_check_detects_bias() {
+    let mut state = LuckState::new(42);
+    let observed = observe(4, 100_000, || chances(&mut state, &[1, 2, 3, 4], None));
+    assert_distribution(&observed, &[1, 2, 3, 5], Significance::Loose);
+}
+
+/// Makes the same draws a battle would, returning the results
+fn battle(luck: &mut ReplayLuck, gems: &RandomPool<char>) -> Result<Vec<u64>, ReplayError> {
+    let mut results = vec![];
+    for _ in 0..5 {
+        results.push(*luck.get("refill", gems)? as u64);
+    }
+    results.push(luck.chances("enemy", &[1, 2, 3])? as u64);
+    results.push(luck.gen_range("damage", 10..20)?);
+    Ok(results)
+}
+
+#[test]
+fn replay_plays_back_recording() {
+    let gems = RandomPool::equal(vec!['r', 'g', 'b']);
+    let mut recording = ReplayLuck::record(LuckState::new(42));
+    let expected = battle(&mut recording, &gems).unwrap();
+    assert_eq!(recording.records().len(), 7);
+    assert_eq!(recording.records()[5].label, "enemy");
+    assert_eq!(recording.records()[6].range, 10..20);
+
+    let saved = serde_json::to_string(recording.records()).unwrap();
+    let mut playback = ReplayLuck::playback(serde_json::from_str(&saved).unwrap());
+    assert!(!playback.is_finished());
+    assert_eq!(battle(&mut playback, &gems).unwrap(), expected);
+    assert!(playback.is_finished());
+}
+
+#[test]
+fn replay_reports_divergence() {
+    let gems = RandomPool::equal(vec!['r', 'g', 'b']);
+    let mut recording = ReplayLuck::record(LuckState::new(42));
+    battle(&mut recording, &gems).unwrap();
+    let records = recording.into_records();
+
+    // Pool got an extra item
+    let mut playback = ReplayLuck::playback(records.clone());
+    let error = battle(&mut playback, &RandomPool::equal(vec!['r', 'g', 'b', 'h'])).unwrap_err();
+    assert_eq!(
+        error,
+        ReplayError::RangeMismatch {
+            index: 0,
+            label: "refill".to_string(),
+            expected: 0..3,
+            found: 0..4,
+        }
+    );
+
+    // Some system made an extra draw
+    let mut playback = ReplayLuck::playback(records.clone());
+    playback.gen_range("crit", 0..100).unwrap_err();
+    let error = playback.gen_range("crit", 0..100).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Replay diverged at draw #0: expected a draw from `refill`, but `crit` drew instead"
+    );
+
+    let mut playback = ReplayLuck::playback(records);
+    battle(&mut playback, &gems).unwrap();
+    assert_eq!(
+        playback.gen_range("damage", 10..20),
+        Err(ReplayError::Exhausted {
+            index: 7,
+            label: "damage".to_string()
+        })
+    );
+}
+
+#[test]
+fn tiny_float_weights_are_kept() {
+    let pool = RandomPool::from_weights([(0.0000001, 'a'), (1.0, 'b')]);
+    let [tiny, one] = pool.weights() else {
+        unreachable!()
+    };
+    assert!((*one as f64 / *tiny as f64 - 1e7).abs() < 1e-3);
+
+    let pool = RandomPool::from_weights([(1e30, 'a'), (3e30, 'b')]);
+    assert!((pool.weights()[1] as f64 / pool.weights()[0] as f64 - 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn exact_weights() {
+    let pool = RandomPool::from_weights([
+        (Rational::new(1, 3), 'a'),
+        (Rational::new(1, 2), 'b'),
+        (Rational::from(2), 'c'),
+    ]);
+    assert_eq!(pool.weights(), &[2, 3, 12]);
+
+    let pool = RandomPool::from_weights([("12.5%", 'a'), ("0.25", 'b'), ("1/8", 'c'), ("1", 'd')]);
+    assert_eq!(pool.weights(), &[1, 2, 1, 8]);
+
+    let pool = RandomPool::from_weights([(FixedWeight::<1000>(125), 'a'), (FixedWeight(875), 'b')]);
+    assert_eq!(pool.weights(), &[125, 875]);
+}
+
+#[test]
+fn invalid_weights_are_reported() {
+    assert_eq!(
+        RandomPool::try_new(vec!['a', 'b'], vec![1]).unwrap_err(),
+        WeightError::LengthMismatch {
+            items: 2,
+            weights: 1
+        }
+    );
+    assert_eq!(
+        RandomPool::try_new(vec!['a', 'b'], vec![0, 0]).unwrap_err(),
+        WeightError::ZeroSum
+    );
+    assert_eq!(
+        RandomPool::<char>::try_new(vec![], vec![]).unwrap_err(),
+        WeightError::ZeroSum
+    );
+    assert_eq!(
+        RandomPool::try_new(vec!['a', 'b'], vec![u64::MAX, 1]).unwrap_err(),
+        WeightError::Overflow
+    );
+    assert_eq!(
+        RandomPool::try_from_weights([(1.0, 'a'), (-1.0, 'b')]).unwrap_err(),
+        WeightError::Negative { index: 1 }
+    );
+    assert_eq!(
+        RandomPool::try_from_weights([(f64::NAN, 'a')]).unwrap_err(),
+        WeightError::NotFinite { index: 0 }
+    );
+    assert_eq!(
+        RandomPool::try_from_weights([(1e300, 'a'), (1e-300, 'b')]).unwrap_err(),
+        WeightError::Underflow { index: 1 }
+    );
+    assert_eq!(
+        RandomPool::try_from_weights([(Rational::new(1, 0), 'a')]).unwrap_err(),
+        WeightError::ZeroDenominator { index: 0 }
+    );
+    for input in ["", "%", "abc", "1/0", "-5", "1.2.3"] {
+        assert_eq!(
+            RandomPool::try_from_weights([("1", 'a'), (input, 'b')]).unwrap_err(),
+            WeightError::Parse {
+                index: 1,
+                input: input.to_string()
+            }
+        );
+    }
+    assert_eq!(
+        RandomPool::try_from_weights([
+            (Rational::new(1, u64::MAX), 'a'),
+            (Rational::new(1, u64::MAX - 1), 'b')
+        ])
+        .unwrap_err(),
+        WeightError::Overflow
+    );
+
+    let mut pool = DynamicPool::new(vec!['a', 'b'], vec![1, 0]);
+    assert_eq!(pool.set_weight(0, 0), Err(WeightError::ZeroSum));
+    assert_eq!(pool.set_weight(1, u64::MAX), Err(WeightError::Overflow));
+    assert_eq!(pool.weights(), &[1, 0]);
+}
@@ -0,0 +1,237 @@
+use std::fmt::{Display, Formatter};
+
+/// Error of converting or validating pool weights
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum WeightError {
+    /// Number of weights doesn't match the number of items
+    LengthMismatch { items: usize, weights: usize },
+    /// All weights are zero, or there are no weights at all
+    ZeroSum,
+    /// Sum of the weights doesn't fit into u64
+    Overflow,
+    /// Weight is negative
+    Negative { index: usize },
+    /// Weight is NaN or infinite
+    NotFinite { index: usize },
+    /// Non-zero weight is too small compared to the other weights, and would
+    /// never be picked
+    Underflow { index: usize },
+    /// Rational weight has zero denominator
+    ZeroDenominator { index: usize },
+    /// Weight string couldn't be parsed
+    Parse { index: usize, input: String },
+}
+
+impl Display for WeightError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeightError::LengthMismatch { items, weights } => {
+                write!(f, "Pool has {items} items, but {weights} weights")
+            }
+            WeightError::ZeroSum => write!(f, "Sum of the pool weights is zero"),
+            WeightError::Overflow => write!(f, "Sum of the pool weights is too large"),
+            WeightError::Negative { index } => write!(f, "Weight #{index} is negative"),
+            WeightError::NotFinite { index } => write!(f, "Weight #{index} is not finite"),
+            WeightError::Underflow { index } => {
+                write!(f, "Weight #{index} is too small compared to other weights")
+            }
+            WeightError::ZeroDenominator { index } => {
+                write!(f, "Weight #{index} has zero denominator")
+            }
+            WeightError::Parse { index, input } => {
+                write!(f, "Weight #{index} `{input}` is not a valid weight")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WeightError {}
+
+/// Checks that weights can be sampled from, returning their sum
+pub fn validate_weights(weights: &[u64]) -> Result<u64, WeightError> {
+    let sum = weights
+        .iter()
+        .try_fold(0u64, |sum, &w| sum.checked_add(w))
+        .ok_or(WeightError::Overflow)?;
+    if sum == 0 {
+        return Err(WeightError::ZeroSum);
+    }
+    Ok(sum)
+}
+
+/// Type that can be used as pool weight
+///
+/// Weights are converted all at once, so fractional weights can be brought
+/// to a common scale
+pub trait PoolWeight: Sized {
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError>;
+}
+
+impl PoolWeight for u64 {
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError> {
+        Ok(weights)
+    }
+}
+
+/// Sum of the scaled float weights, leaving headroom for rounding up
+const FLOAT_SCALE_LIMIT: f64 = (1u64 << 62) as f64;
+
+impl PoolWeight for f64 {
+    /// Scales weights by the largest power of two that keeps their sum in
+    /// u64, so even tiny weights keep their proportions
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError> {
+        for (index, &w) in weights.iter().enumerate() {
+            if !w.is_finite() {
+                return Err(WeightError::NotFinite { index });
+            }
+            if w < 0.0 {
+                return Err(WeightError::Negative { index });
+            }
+        }
+        let sum: f64 = weights.iter().sum();
+        if !sum.is_finite() {
+            return Err(WeightError::Overflow);
+        }
+        if sum == 0.0 {
+            return Err(WeightError::ZeroSum);
+        }
+
+        // Multiplying by powers of two is exact
+        let mut scale = 1.0;
+        while sum * scale > FLOAT_SCALE_LIMIT {
+            scale /= 2.0;
+        }
+        while sum * scale * 2.0 <= FLOAT_SCALE_LIMIT {
+            scale *= 2.0;
+        }
+
+        weights
+            .into_iter()
+            .enumerate()
+            .map(|(index, w)| {
+                let scaled = (w * scale).round() as u64;
+                if scaled == 0 && w > 0.0 {
+                    Err(WeightError::Underflow { index })
+                } else {
+                    Ok(scaled)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Exact fractional weight
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rational {
+    pub numer: u64,
+    pub denom: u64,
+}
+
+impl Rational {
+    pub fn new(numer: u64, denom: u64) -> Self {
+        Self { numer, denom }
+    }
+}
+
+impl From<u64> for Rational {
+    fn from(value: u64) -> Self {
+        Self::new(value, 1)
+    }
+}
+
+impl PoolWeight for Rational {
+    /// Brings weights to the least common denominator
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError> {
+        let mut common = 1u64;
+        for (index, w) in weights.iter().enumerate() {
+            if w.denom == 0 {
+                return Err(WeightError::ZeroDenominator { index });
+            }
+            if w.numer == 0 {
+                continue;
+            }
+            let denom = w.denom / gcd(w.numer, w.denom);
+            common = (common / gcd(common, denom))
+                .checked_mul(denom)
+                .ok_or(WeightError::Overflow)?;
+        }
+        weights
+            .into_iter()
+            .map(|w| {
+                let scaled = w.numer as u128 * common as u128 / w.denom as u128;
+                u64::try_from(scaled).map_err(|_| WeightError::Overflow)
+            })
+            .collect()
+    }
+}
+
+/// Fixed-point weight, holding `raw / SCALE`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FixedWeight<const SCALE: u64>(pub u64);
+
+impl<const SCALE: u64> PoolWeight for FixedWeight<SCALE> {
+    /// All weights share the same scale, so raw values are used as is
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError> {
+        Ok(weights.into_iter().map(|w| w.0).collect())
+    }
+}
+
+/// Weights written as decimals (`"0.25"`), percentages (`"12.5%"`) or
+/// fractions (`"1/3"`), converted exactly
+impl PoolWeight for &str {
+    fn into_weights(weights: Vec<Self>) -> Result<Vec<u64>, WeightError> {
+        let weights = weights
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                parse_rational(input).ok_or_else(|| WeightError::Parse {
+                    index,
+                    input: input.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Rational::into_weights(weights)
+    }
+}
+
+fn parse_rational(input: &str) -> Option<Rational> {
+    let input = input.trim();
+    if let Some((numer, denom)) = input.split_once('/') {
+        let denom = denom.trim().parse().ok().filter(|&d| d != 0)?;
+        return Some(Rational::new(numer.trim().parse().ok()?, denom));
+    }
+
+    let (number, mut denom) = match input.strip_suffix('%') {
+        Some(number) => (number.trim_end(), 100u64),
+        None => (input, 1),
+    };
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut numer = 0u64;
+    for digit in whole.bytes().chain(fraction.bytes()) {
+        numer = numer.checked_mul(10)?.checked_add((digit - b'0') as u64)?;
+    }
+    for _ in 0..fraction.len() {
+        denom = denom.checked_mul(10)?;
+    }
+    Some(Rational::new(numer, denom))
+}
+
+pub(crate) fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
@@ -5,21 +5,52 @@ use colored::Colorize;
 use itertools::Itertools;
 use nohash_hasher::IntSet;
 
-#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub struct CharGem(pub char);
 
+/// Colors matched by the multi-color gems
+fn multi_color_components(c: char) -> &'static [char] {
+    match c {
+        // Red/blue "violet" gem
+        'v' => &['r', 'b'],
+        _ => &[],
+    }
+}
+
 impl MatchColor for CharGem {
     fn matches(&self, other: &Self) -> bool {
-        self.0 == other.0 || self.0 == '*' || other.0 == '*'
+        self.0 == other.0
+            || self.0 == '*'
+            || other.0 == '*'
+            || multi_color_components(self.0).contains(&other.0)
+            || multi_color_components(other.0).contains(&self.0)
     }
 
     fn can_start_match(&self) -> bool {
         self.0 != '*' && self.0 != '-' && self.0 != ' '
     }
 
+    fn narrow(&self, other: &Self) -> Self {
+        if multi_color_components(self.0).contains(&other.0) {
+            *other
+        } else {
+            *self
+        }
+    }
+
     fn hint_is_unmatchable(&self) -> bool {
         self.0 == '-' || self.0 == ' '
     }
+
+    fn line_size(&self) -> Option<usize> {
+        match self.0 {
+            // "Heart" gems, matching in pairs
+            'h' => Some(2),
+            // "Heavy" gems, requiring 4-in-a-row
+            'x' => Some(4),
+            _ => None,
+        }
+    }
 }
 
 impl BoardGem for CharGem {
@@ -170,7 +201,7 @@ pub fn visualize_and_apply_matches(
 }
 
 pub fn visualise_and_apply_gravity(board: &mut CharBoard) -> String {
-    let actions = GravityRefill::refill(&board.board, board.vertical_lines());
+    let actions = GravityRefill::refill(&board.board, board.gravity_lines());
     let (falling, refilling) = actions
         .iter()
         .partition::<Vec<_>, _>(|a| matches!(a, GravityRefillAction::Fall(_)));
@@ -0,0 +1,241 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use itertools::Itertools;
+use ndshape::Shape;
+
+use crate::history::BoardOperation;
+use crate::rect_board::{ColumnPortal, RectBoard, RectTopology};
+use crate::{BoardGem, MatchColor};
+
+/// Mirroring transformation of a rectangular board
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BoardMirror {
+    None,
+    /// Flips the board left to right
+    Horizontal,
+    /// Flips the board upside down
+    Vertical,
+    /// Flips the board in both directions, same as rotating it by 180 degrees
+    Both,
+}
+
+impl BoardMirror {
+    pub const ALL: [BoardMirror; 4] = [
+        BoardMirror::None,
+        BoardMirror::Horizontal,
+        BoardMirror::Vertical,
+        BoardMirror::Both,
+    ];
+
+    /// Mirrors that keep the board playing the same way, as gravity always
+    /// pulls gems down and an upside-down board plays differently
+    pub const GRAVITY_PRESERVING: [BoardMirror; 2] = [BoardMirror::None, BoardMirror::Horizontal];
+
+    /// Maps cell coordinates to the mirrored ones
+    pub fn apply(self, [x, y]: [usize; 2], [width, height]: [usize; 2]) -> [usize; 2] {
+        let flip_x = matches!(self, BoardMirror::Horizontal | BoardMirror::Both);
+        let flip_y = matches!(self, BoardMirror::Vertical | BoardMirror::Both);
+        [
+            if flip_x { width - x - 1 } else { x },
+            if flip_y { height - y - 1 } else { y },
+        ]
+    }
+
+    /// Maps portals of the topology to the mirrored columns
+    ///
+    /// Vertical flips also swap the ends of each portal, as the bottom of the
+    /// source column ends up on top
+    pub fn apply_topology(self, topology: &RectTopology, width: usize) -> RectTopology {
+        let reverse = matches!(self, BoardMirror::Vertical | BoardMirror::Both);
+        let mirror_x = |x| self.apply([x, 0], [width, 1])[0];
+        RectTopology {
+            portals: topology
+                .portals
+                .iter()
+                .map(|portal| {
+                    let (from, to) = if reverse {
+                        (portal.to, portal.from)
+                    } else {
+                        (portal.from, portal.to)
+                    };
+                    ColumnPortal {
+                        from: mirror_x(from),
+                        to: mirror_x(to),
+                    }
+                })
+                .collect(),
+            ..topology.clone()
+        }
+    }
+}
+
+/// Order-independent description of the topology, for comparing and hashing
+fn topology_key(topology: &RectTopology) -> (bool, bool, Vec<(usize, usize)>) {
+    (
+        topology.wrap_horizontal,
+        topology.wrap_vertical,
+        topology
+            .portals
+            .iter()
+            .map(|portal| (portal.from, portal.to))
+            .sorted()
+            .collect(),
+    )
+}
+
+/// Color of a cell in the canonical board form
+///
+/// Colors that can't start a match, like empty cells or wildcards, are kept
+/// as is, and so are colors matching other colors, like the components of
+/// multi-color gems. Other colors are numbered in the order of appearance,
+/// separately for each set of matching rules
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum CanonicalColor<Color> {
+    Fixed(Color),
+    Relabeled {
+        line_size: Option<usize>,
+        min_group_size: Option<usize>,
+        label: usize,
+    },
+}
+
+impl<T: BoardGem + Clone + PartialEq> RectBoard<T> {
+    /// Returns the list of cell replacements that turn this board into the
+    /// `other` one
+    ///
+    /// # Panics
+    /// Panics if boards are of different sizes
+    pub fn diff(&self, other: &Self) -> Vec<BoardOperation<T>> {
+        assert_eq!(
+            self.shape.as_array(),
+            other.shape.as_array(),
+            "Can't diff boards of different sizes"
+        );
+        self.board
+            .iter()
+            .zip(other.board.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(pos, (old, new))| BoardOperation::Replace {
+                pos,
+                old: old.clone(),
+                new: new.clone(),
+            })
+            .collect()
+    }
+
+    /// Applies the diff produced by [RectBoard::diff]
+    ///
+    /// Patches can be reverted by applying the inverse of each operation in
+    /// reverse order
+    pub fn patch(&mut self, diff: &[BoardOperation<T>]) {
+        for operation in diff {
+            operation.apply(&mut self.board);
+        }
+    }
+}
+
+impl<T: BoardGem + Clone> RectBoard<T> {
+    /// Returns a mirrored copy of the board, with portals mirrored as well
+    pub fn mirrored(&self, mirror: BoardMirror) -> Self {
+        let size = self.shape.as_array();
+        let board = (0..self.shape.size())
+            .map(|i| {
+                let source = mirror.apply(self.shape.delinearize(i), size);
+                self.board[self.shape.linearize(source)].clone()
+            })
+            .collect();
+        let topology = mirror.apply_topology(&self.topology, size[0]);
+        Self::with_topology(size[0], size[1], board, topology)
+    }
+}
+
+impl<T: BoardGem> RectBoard<T>
+where
+    T::Color: Hash + Eq,
+{
+    /// Returns colors of the board, as seen through the mirror, with
+    /// interchangeable colors numbered in the order of appearance
+    pub fn canonical_colors(&self, mirror: BoardMirror) -> Vec<CanonicalColor<T::Color>> {
+        let size = self.shape.as_array();
+        let fixed = self.fixed_colors();
+        let mut labels = HashMap::<T::Color, usize>::new();
+        let mut counts = HashMap::<(Option<usize>, Option<usize>), usize>::new();
+        (0..self.shape.size())
+            .map(|i| {
+                let color = self.board[self
+                    .shape
+                    .linearize(mirror.apply(self.shape.delinearize(i), size))]
+                .color();
+                if !color.can_start_match() || fixed.contains(&color) {
+                    return CanonicalColor::Fixed(color);
+                }
+                let (line_size, min_group_size) = (color.line_size(), color.min_group_size());
+                let label = *labels.entry(color).or_insert_with(|| {
+                    let count = counts.entry((line_size, min_group_size)).or_default();
+                    *count += 1;
+                    *count - 1
+                });
+                CanonicalColor::Relabeled {
+                    line_size,
+                    min_group_size,
+                    label,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns colors that match other colors of the board, like the
+    /// components of multi-color gems, so swapping them for other colors
+    /// changes which gems match
+    fn fixed_colors(&self) -> HashSet<T::Color> {
+        self.board
+            .iter()
+            .map(|gem| gem.color())
+            .filter(|color| color.can_start_match())
+            .unique()
+            .tuple_combinations()
+            .filter(|(a, b)| a.matches(b) || b.matches(a))
+            .flat_map(|(a, b)| [a, b])
+            .collect()
+    }
+
+    /// Hash that is the same for boards equivalent up to color permutation
+    /// and horizontal mirroring, see [RectBoard::is_equivalent]
+    ///
+    /// Hash values are only stable within a single build of the game
+    pub fn canonical_hash(&self) -> u64 {
+        let width = self.width();
+        BoardMirror::GRAVITY_PRESERVING
+            .into_iter()
+            .map(|mirror| {
+                let mut hasher = DefaultHasher::new();
+                self.shape.as_array().hash(&mut hasher);
+                topology_key(&mirror.apply_topology(&self.topology, width)).hash(&mut hasher);
+                self.canonical_colors(mirror).hash(&mut hasher);
+                hasher.finish()
+            })
+            .min()
+            .expect("Should have at least one mirror")
+    }
+
+    /// Checks whenever two boards are the same up to color permutation and
+    /// horizontal mirroring, see [BoardMirror::GRAVITY_PRESERVING]
+    ///
+    /// Only colors that can start a match get permuted, and mirrors only
+    /// count when they map the topology of `other` onto the topology of this
+    /// board
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        if self.shape.as_array() != other.shape.as_array() {
+            return false;
+        }
+        let topology = topology_key(&self.topology);
+        let colors = self.canonical_colors(BoardMirror::None);
+        BoardMirror::GRAVITY_PRESERVING.into_iter().any(|mirror| {
+            topology_key(&mirror.apply_topology(&other.topology, other.width())) == topology
+                && other.canonical_colors(mirror) == colors
+        })
+    }
+}
@@ -0,0 +1,215 @@
+use crate::rect_board::{GridMoveStrategy, RectBoard};
+use crate::refilling::GravityRefillAction;
+use crate::{BoardGem, BoardMatch};
+
+/// Invertible mutation of a board
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BoardOperation<Gem> {
+    /// Gems in two cells were swapped
+    Swap { a: usize, b: usize },
+    /// Gem in a cell was replaced with another one
+    Replace { pos: usize, old: Gem, new: Gem },
+}
+
+impl<Gem: Clone> BoardOperation<Gem> {
+    pub fn apply(&self, board: &mut [Gem]) {
+        match self {
+            BoardOperation::Swap { a, b } => board.swap(*a, *b),
+            BoardOperation::Replace { pos, new, .. } => board[*pos] = new.clone(),
+        }
+    }
+
+    /// Returns an operation that reverts this one
+    pub fn inverse(&self) -> Self {
+        match self {
+            BoardOperation::Swap { a, b } => BoardOperation::Swap { a: *a, b: *b },
+            BoardOperation::Replace { pos, old, new } => BoardOperation::Replace {
+                pos: *pos,
+                old: new.clone(),
+                new: old.clone(),
+            },
+        }
+    }
+}
+
+/// Board wrapper that records mutations, allowing to undo and redo them
+///
+/// Mutations are grouped into turns, started by [BoardHistory::begin_turn].
+/// Recording a new mutation after an undo discards all redoable steps
+#[derive(Debug, Clone)]
+pub struct BoardHistory<T: BoardGem> {
+    board: RectBoard<T>,
+    steps: Vec<BoardOperation<T>>,
+    /// Indices of the first step of each turn
+    turns: Vec<usize>,
+    /// Number of currently applied steps
+    cursor: usize,
+}
+
+impl<T: BoardGem + Clone> BoardHistory<T> {
+    pub fn new(board: RectBoard<T>) -> Self {
+        Self {
+            board,
+            steps: vec![],
+            turns: vec![],
+            cursor: 0,
+        }
+    }
+
+    pub fn board(&self) -> &RectBoard<T> {
+        &self.board
+    }
+
+    pub fn into_board(self) -> RectBoard<T> {
+        self.board
+    }
+
+    /// Returns all recorded steps, including undone ones
+    pub fn steps(&self) -> &[BoardOperation<T>] {
+        &self.steps
+    }
+
+    /// Number of currently applied steps
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Marks the start of a new turn at the current position
+    ///
+    /// Undone steps stay redoable until a new mutation is recorded
+    pub fn begin_turn(&mut self) {
+        if let Err(index) = self.turns.binary_search(&self.cursor) {
+            self.turns.insert(index, self.cursor);
+        }
+    }
+
+    /// Applies and records the operation
+    pub fn apply(&mut self, operation: BoardOperation<T>) {
+        self.discard_redo();
+        operation.apply(&mut self.board.board);
+        self.steps.push(operation);
+        self.cursor += 1;
+    }
+
+    /// Swaps gems in two cells
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.apply(BoardOperation::Swap { a, b });
+    }
+
+    /// Replaces the gem in a cell
+    pub fn replace(&mut self, pos: usize, gem: T) {
+        let old = self.board.board[pos].clone();
+        self.apply(BoardOperation::Replace { pos, old, new: gem });
+    }
+
+    /// Moves the gem along the path returned by [RectBoard::move_gem],
+    /// swapping it with every gem on the way
+    ///
+    /// Returns the cells the gem passed through
+    pub fn move_gem(&mut self, from: usize, to: usize, strategy: GridMoveStrategy) -> Vec<usize> {
+        let path: Vec<usize> = self.board.move_gem(from, to, strategy).collect();
+        let mut held = from;
+        for &cell in &path {
+            self.swap(held, cell);
+            held = cell;
+        }
+        path
+    }
+
+    /// Recorded counterpart of [crate::refilling::remove_matched]
+    pub fn remove_matched(
+        &mut self,
+        matches: &[BoardMatch<T::Color>],
+        mut empty_gem: impl FnMut() -> T,
+    ) {
+        for &c in matches.iter().flat_map(|g| g.cells.iter()) {
+            self.replace(c, empty_gem());
+        }
+    }
+
+    /// Recorded counterpart of [GravityRefillAction::apply]
+    pub fn apply_refill(
+        &mut self,
+        action: &GravityRefillAction,
+        mut random_gem: impl FnMut(usize) -> T,
+    ) {
+        match action {
+            GravityRefillAction::Fall(fall) => self.swap(fall.from, fall.to),
+            GravityRefillAction::FallIn(fall) => self.replace(fall.pos, random_gem(fall.pos)),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.cursor < self.steps.len()
+    }
+
+    /// Reverts a single step, returning `false` if there is nothing to undo
+    pub fn undo_step(&mut self) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        self.cursor -= 1;
+        self.steps[self.cursor]
+            .inverse()
+            .apply(&mut self.board.board);
+        true
+    }
+
+    /// Re-applies a single undone step, returning `false` if there is nothing
+    /// to redo
+    pub fn redo_step(&mut self) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+        self.steps[self.cursor].apply(&mut self.board.board);
+        self.cursor += 1;
+        true
+    }
+
+    /// Reverts all steps of the latest applied turn, returning `false` if
+    /// there is nothing to undo
+    pub fn undo_turn(&mut self) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let target = self
+            .turns
+            .iter()
+            .rev()
+            .copied()
+            .find(|&start| start < self.cursor)
+            .unwrap_or(0);
+        while self.cursor > target {
+            self.undo_step();
+        }
+        true
+    }
+
+    /// Re-applies all steps of the next undone turn, returning `false` if
+    /// there is nothing to redo
+    pub fn redo_turn(&mut self) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+        let target = self
+            .turns
+            .iter()
+            .copied()
+            .find(|&start| start > self.cursor)
+            .unwrap_or(self.steps.len());
+        while self.cursor < target {
+            self.redo_step();
+        }
+        true
+    }
+
+    fn discard_redo(&mut self) {
+        self.steps.truncate(self.cursor);
+        let cursor = self.cursor;
+        self.turns.retain(|&start| start <= cursor);
+    }
+}
@@ -12,6 +12,8 @@ pub mod tests;
 #[cfg(feature = "char-board")]
 pub mod char_board;
 
+pub mod diff;
+pub mod history;
 pub mod line;
 
 pub mod rect_board;
@@ -32,6 +34,16 @@ pub trait MatchColor: Debug + Clone + Default {
     /// Should ideally be set to false for "wildcard" colors
     fn can_start_match(&self) -> bool;
 
+    /// Narrows down the color of a match group after a gem of `other` color
+    /// joins it
+    ///
+    /// Multi-color gems should override this to return the more specific
+    /// color, so a red/blue gem followed by a red gem forms a red group that
+    /// blue gems can no longer join
+    fn narrow(&self, _other: &Self) -> Self {
+        self.clone()
+    }
+
     /// Hints to the matcher that the color should not be checked for matching
     ///
     /// This method is mainly for performance optimization and matchers are
@@ -40,6 +52,22 @@ pub trait MatchColor: Debug + Clone + Default {
     fn hint_is_unmatchable(&self) -> bool {
         false
     }
+
+    /// Minimum line size for matches started by this color
+    ///
+    /// Returning `None` makes the matcher fall back to the global setting,
+    /// like [line::LineMatcherSettings::line_size]
+    fn line_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Minimum size of a match group started by this color
+    ///
+    /// Returning `None` makes the matcher fall back to the global setting,
+    /// like [line::LineMatcherSettings::min_group_size]
+    fn min_group_size(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub trait BoardGem {
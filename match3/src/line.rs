@@ -21,6 +21,20 @@ impl Hash for MatchIndex {
 
 impl IsEnabled for MatchIndex {}
 
+/// Resolution policy for gems that could join two different groups in a
+/// single line, like a wildcard sitting between a red and a blue group
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum WildcardPolicy {
+    /// Shared gems join both groups
+    #[default]
+    JoinBoth,
+    /// Shared gems stay in the group that comes first in the line
+    PreferFirst,
+    /// Shared gems join the longest group, or the first one if groups are of
+    /// the same size
+    PreferLongest,
+}
+
 #[derive(Debug, Clone)]
 pub struct LineMatcherSettings {
     /// Minimum line size
@@ -30,6 +44,8 @@ pub struct LineMatcherSettings {
     /// Whenever neighbouring valid groups of matching types should be merged
     /// regardless of the [line_size]
     pub merge_neighbours: bool,
+    /// How gems shared between two groups of the same line are resolved
+    pub wildcard_policy: WildcardPolicy,
 }
 
 impl LineMatcherSettings {
@@ -38,6 +54,7 @@ impl LineMatcherSettings {
             line_size,
             min_group_size,
             merge_neighbours,
+            wildcard_policy: Default::default(),
         }
     }
 
@@ -56,12 +73,29 @@ impl LineMatcherSettings {
         self
     }
 
+    pub fn with_wildcard_policy(mut self, wildcard_policy: WildcardPolicy) -> Self {
+        self.wildcard_policy = wildcard_policy;
+        self
+    }
+
     /// Common match-3 settings, with 3-in-a-row matches and no neighboring
     /// groups merging
     pub fn common_match3() -> Self {
         Self::new(3, None, false)
     }
 
+    /// Minimum line size for a group of the given color, respecting
+    /// [MatchColor::line_size] overrides
+    pub fn line_size_for<Color: MatchColor>(&self, color: &Color) -> usize {
+        color.line_size().unwrap_or(self.line_size)
+    }
+
+    /// Minimum size of a match group of the given color, respecting
+    /// [MatchColor::min_group_size] overrides
+    pub fn min_group_size_for<Color: MatchColor>(&self, color: &Color) -> Option<usize> {
+        color.min_group_size().or(self.min_group_size)
+    }
+
     pub fn find_matches<
         'a,
         Gem: crate::BoardGem,
@@ -137,7 +171,7 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
     }
 
     fn close_match(&mut self, mut group: BoardMatch<Gem::Color>) {
-        if group.cells.len() < self.settings.line_size {
+        if group.cells.len() < self.settings.line_size_for(&group.color) {
             return;
         }
 
@@ -153,21 +187,37 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
             let other_groups = &match_board[cell];
 
             for &intersecting in other_groups.iter() {
-                // Resolve the other group ID
-                let other_group = &mut matches[intersecting.0]
-                    .as_mut()
-                    .expect("All dead groups should be inaccessible from the board");
-                if !other_group.color.matches(&group.color) {
+                if *merge_group == Some(intersecting) {
                     continue;
                 }
-                if let Some(group) = merge_group {
-                    if group == &intersecting {
+                // Resolve the other group ID
+                let other_color = matches[intersecting.0]
+                    .as_ref()
+                    .expect("All dead groups should be inaccessible from the board")
+                    .color
+                    .clone();
+                if let Some(merged) = *merge_group {
+                    // Colors get narrowed down with every merge, so a
+                    // multi-color group can't chain groups of its different
+                    // colors together
+                    let main = matches[merged.0]
+                        .as_mut()
+                        .expect("Merge group was checked for already");
+                    if !main.color.matches(&other_color) {
                         continue;
                     }
+                    main.color = main.color.narrow(&other_color);
                     // We already found a group to merge into, so add this matching group to merge in at a later stage
                     groups_to_merge.insert(intersecting);
                 } else {
+                    if !other_color.matches(&group.color) {
+                        continue;
+                    }
                     // We intersect with the first matching group, so merge the current group into that one
+                    let other_group = matches[intersecting.0]
+                        .as_mut()
+                        .expect("All dead groups should be inaccessible from the board");
+                    other_group.color = other_color.narrow(&group.color);
                     other_group.cells.extend(group.cells());
                     *merge_group = Some(intersecting);
                 }
@@ -190,7 +240,9 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
             );
         }
 
-        if merge_group.is_none() && group.cells.len() < self.settings.min_group_size.unwrap_or(0) {
+        if merge_group.is_none()
+            && group.cells.len() < self.settings.min_group_size_for(&group.color).unwrap_or(0)
+        {
             group.cells.clear();
             return;
         }
@@ -308,12 +360,64 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
         }
     }
 
-    fn match_line(&mut self, line: &[usize]) {
-        if line.len() < self.settings.line_size {
+    /// Resolves gems shared by two consecutive groups of the same line
+    /// according to the [WildcardPolicy]
+    fn resolve_shared_cells(
+        &self,
+        previous: &mut BoardMatch<Gem::Color>,
+        group: &mut BoardMatch<Gem::Color>,
+    ) {
+        let Some(&first) = group.cells.first() else {
             return;
+        };
+        let Some(start) = previous.cells.iter().rposition(|&c| c == first) else {
+            return;
+        };
+        let shared = previous.cells.len() - start;
+        debug_assert_eq!(
+            &previous.cells[start..],
+            &group.cells[..shared],
+            "Shared cells should be a suffix of the previous group and a prefix of the next one"
+        );
+
+        let previous_valid = previous.cells.len() >= self.settings.line_size_for(&previous.color);
+        let group_valid = group.cells.len() >= self.settings.line_size_for(&group.color);
+
+        let keep_in_previous = match (self.settings.wildcard_policy, previous_valid, group_valid) {
+            (WildcardPolicy::JoinBoth, _, _) | (_, false, false) => return,
+            (_, true, false) => true,
+            (_, false, true) => false,
+            (WildcardPolicy::PreferFirst, true, true) => true,
+            (WildcardPolicy::PreferLongest, true, true) => {
+                previous.cells.len() >= group.cells.len()
+            }
+        };
+
+        if keep_in_previous {
+            group.cells.drain(..shared);
+        } else {
+            previous.cells.truncate(start);
         }
+    }
+
+    /// Closes the previous group of the line, keeping the new one pending
+    /// until its neighbour in the line is known
+    fn push_line_match(
+        &mut self,
+        pending: &mut Option<BoardMatch<Gem::Color>>,
+        mut group: BoardMatch<Gem::Color>,
+    ) {
+        if let Some(mut previous) = pending.take() {
+            self.resolve_shared_cells(&mut previous, &mut group);
+            self.close_match(previous);
+        }
+        *pending = Some(group);
+    }
+
+    fn match_line(&mut self, line: &[usize]) {
         let mut current_match: Option<BoardMatch<Gem::Color>> = None;
-        let mut was_wildcard = false;
+        let mut pending_match: Option<BoardMatch<Gem::Color>> = None;
+        let mut previous_matchable = false;
         for i in 0..line.len() {
             let pos = line[i];
             let gem = &self.cells[pos].color();
@@ -324,8 +428,9 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
                 if !match_group.color.matches(gem) {
                     let group = std::mem::take(&mut current_match)
                         .expect("Should have a match group to close");
-                    self.close_match(group);
+                    self.push_line_match(&mut pending_match, group);
                 } else {
+                    match_group.color = match_group.color.narrow(gem);
                     match_group.cells.push(pos);
                 }
             }
@@ -333,33 +438,40 @@ impl<'a, Gem: crate::BoardGem, Line: AsRef<[usize]>, Neighbours: AsRef<[usize]>>
             if current_match.is_none() && can_start_match && can_be_matched {
                 let mut group = BoardMatch::<Gem::Color>::new(gem.clone());
 
-                if was_wildcard {
+                // Wildcards and multi-color gems preceding the group can
+                // also be a part of it
+                if previous_matchable {
                     for i in (0..i).rev() {
                         let back_pos = line[i];
                         let back_gem = &self.cells[back_pos].color();
                         if !group.color.matches(back_gem) {
                             break;
                         }
+                        group.color = group.color.narrow(back_gem);
                         group.cells.insert(0, back_pos);
                     }
                 }
 
                 if group.cells.len() + 1 // actual group size
                     + line.len() - i - 1 // remaining space
-                    < self.settings.line_size
+                    < self.settings.line_size_for(&group.color)
                 {
+                    // Line sizes can differ between colors, so gems further
+                    // down the line still get a chance to start a group
                     group.cells.clear();
-                    break;
-                }
-
-                group.cells.push(pos);
+                } else {
+                    group.cells.push(pos);
 
-                current_match = Some(group);
+                    current_match = Some(group);
+                }
             }
-            was_wildcard = !can_start_match && can_be_matched;
+            previous_matchable = can_be_matched;
         }
 
         if let Some(group) = current_match {
+            self.push_line_match(&mut pending_match, group);
+        }
+        if let Some(group) = pending_match {
             self.close_match(group);
         }
     }
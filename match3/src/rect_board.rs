@@ -1,6 +1,7 @@
 use crate::line::LineMatcherSettings;
-use crate::{BoardGem, BoardMatch};
+use crate::{BoardGem, BoardMatch, MatchColor};
 use ndshape::{RuntimeShape, Shape};
+use std::borrow::Cow;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 
@@ -9,7 +10,107 @@ pub struct RectBoard<T: BoardGem> {
     pub shape: RuntimeShape<usize, 2>,
     pub board: Vec<T>,
     pub lines: Arc<Vec<Vec<usize>>>,
+    pub gravity_lines: Arc<Vec<Vec<usize>>>,
     pub neighbours: Arc<Vec<Vec<usize>>>,
+    pub topology: Arc<RectTopology>,
+}
+
+/// Describes how cells of a [RectBoard] are connected to each other
+#[derive(Debug, Clone, Default)]
+pub struct RectTopology {
+    /// Whenever rows wrap around, making the leftmost and the rightmost
+    /// columns neighbours
+    pub wrap_horizontal: bool,
+    /// Whenever columns wrap around, making the top and the bottom of each
+    /// column neighbours
+    ///
+    /// Gravity still flows from top to bottom on wrapped columns
+    pub wrap_vertical: bool,
+    /// Columns continuing into other columns
+    pub portals: Vec<ColumnPortal>,
+}
+
+/// Portal connecting the bottom of the `from` column to the top of the `to`
+/// column, so gems fall from one column into another and matches can
+/// continue through the portal
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ColumnPortal {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl RectTopology {
+    /// Toroidal topology, with both rows and columns wrapping around
+    pub fn toroidal() -> Self {
+        Self::default()
+            .with_wrap_horizontal(true)
+            .with_wrap_vertical(true)
+    }
+
+    pub fn with_wrap_horizontal(mut self, wrap_horizontal: bool) -> Self {
+        self.wrap_horizontal = wrap_horizontal;
+        self
+    }
+
+    pub fn with_wrap_vertical(mut self, wrap_vertical: bool) -> Self {
+        self.wrap_vertical = wrap_vertical;
+        self
+    }
+
+    pub fn with_portal(mut self, from: usize, to: usize) -> Self {
+        self.portals.push(ColumnPortal { from, to });
+        self
+    }
+
+    /// Checks whenever any portal leads into or out of the column
+    pub fn has_portal(&self, column: usize) -> bool {
+        self.portals
+            .iter()
+            .any(|portal| portal.from == column || portal.to == column)
+    }
+
+    /// Chains columns connected by portals, returning lists of columns from
+    /// top to bottom
+    ///
+    /// # Panics
+    /// Panics if portals are out of bounds, overlap, or form a loop
+    fn column_chains(&self, width: usize) -> Vec<Vec<usize>> {
+        let mut next = vec![None; width];
+        let mut prev = vec![None; width];
+        for portal in &self.portals {
+            assert!(
+                portal.from < width && portal.to < width,
+                "Portal {portal:?} leads outside of the board"
+            );
+            assert!(
+                next[portal.from].replace(portal.to).is_none(),
+                "Column {} has multiple outgoing portals",
+                portal.from
+            );
+            assert!(
+                prev[portal.to].replace(portal.from).is_none(),
+                "Column {} has multiple incoming portals",
+                portal.to
+            );
+        }
+
+        let mut chains = vec![];
+        let mut chained = 0;
+        for (x, prev) in prev.iter().enumerate() {
+            if prev.is_some() {
+                continue;
+            }
+            let mut chain = vec![x];
+            while let Some(next) = next[*chain.last().unwrap()] {
+                chain.push(next);
+            }
+            chained += chain.len();
+            chains.push(chain);
+        }
+        assert_eq!(chained, width, "Portals must not form a loop");
+
+        chains
+    }
 }
 
 impl<T: Debug + BoardGem> Debug for RectBoard<T> {
@@ -36,79 +137,180 @@ impl<T: BoardGem> RectBoard<T> {
     }
 
     pub fn new(width: usize, height: usize, board: Vec<T>) -> Self {
+        Self::with_topology(width, height, board, Default::default())
+    }
+
+    /// Creates a board with custom connections between cells
+    ///
+    /// # Panics
+    /// Panics if the topology has invalid portals, see [RectTopology::portals]
+    pub fn with_topology(
+        width: usize,
+        height: usize,
+        board: Vec<T>,
+        topology: RectTopology,
+    ) -> Self {
         let shape = RuntimeShape::<usize, 2>::new([width, height]);
-        let mut lines: Vec<Vec<usize>> = vec![];
-        let mut neighbours: Vec<Vec<usize>> = vec![];
-        for x in 0..width {
-            lines.push((0..height).map(|y| shape.linearize([x, y])).collect());
-        }
-        for y in 0..height {
-            lines.push((0..width).map(|x| shape.linearize([x, y])).collect());
-        }
 
-        for i in 0..shape.size() {
-            let [x, y] = shape.delinearize(i);
-            let mut cell_neighbours = vec![];
-            if x > 0 {
-                cell_neighbours.push(shape.linearize([x - 1, y]));
-            }
-            if x < width - 1 {
-                cell_neighbours.push(shape.linearize([x + 1, y]));
-            }
-            if y > 0 {
-                cell_neighbours.push(shape.linearize([x, y - 1]));
-            }
-            if y < height - 1 {
-                cell_neighbours.push(shape.linearize([x, y + 1]));
-            }
-            neighbours.push(cell_neighbours);
-        }
+        let gravity_lines: Vec<Vec<usize>> = topology
+            .column_chains(width)
+            .into_iter()
+            .map(|chain| {
+                chain
+                    .into_iter()
+                    .flat_map(|x| (0..height).map(move |y| [x, y]))
+                    .map(|pos| shape.linearize(pos))
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<Vec<usize>> = (0..height)
+            .map(|y| (0..width).map(|x| shape.linearize([x, y])).collect())
+            .collect();
+
+        let mut up = vec![None; shape.size()];
+        let mut down = vec![None; shape.size()];
+        let mut left = vec![None; shape.size()];
+        let mut right = vec![None; shape.size()];
+        link_line_cells(&gravity_lines, topology.wrap_vertical, &mut up, &mut down);
+        link_line_cells(&rows, topology.wrap_horizontal, &mut left, &mut right);
+
+        let neighbours = (0..shape.size())
+            .map(|i| {
+                let mut cell_neighbours = vec![];
+                for neighbour in [left[i], right[i], up[i], down[i]].into_iter().flatten() {
+                    if neighbour != i && !cell_neighbours.contains(&neighbour) {
+                        cell_neighbours.push(neighbour);
+                    }
+                }
+                cell_neighbours
+            })
+            .collect();
+
+        let lines = gravity_lines.iter().chain(rows.iter()).cloned().collect();
 
         Self {
             shape,
             board,
             lines: Arc::new(lines),
+            gravity_lines: Arc::new(gravity_lines),
             neighbours: Arc::new(neighbours),
+            topology: Arc::new(topology),
         }
     }
 
+    /// Returns vertical matching lines, with columns connected by portals
+    /// joined into a single line
+    ///
+    /// Lines of wrapped topologies are connected end to start when matching
     pub fn vertical_lines(&self) -> &[Vec<usize>] {
-        &self.lines[..self.width()]
+        &self.lines[..self.gravity_lines.len()]
     }
 
     pub fn horizontal_lines(&self) -> &[Vec<usize>] {
-        &self.lines[self.width()..]
+        &self.lines[self.gravity_lines.len()..]
+    }
+
+    /// Returns lines along which gems fall, from top to bottom
+    ///
+    /// Unlike [RectBoard::vertical_lines], gravity lines never connect across
+    /// the wrapped edge
+    pub fn gravity_lines(&self) -> &[Vec<usize>] {
+        &self.gravity_lines
     }
 
     pub fn find_matches_linear(&self, settings: &LineMatcherSettings) -> Vec<BoardMatch<T::Color>> {
-        settings.find_matches(&self.board, &self.lines, &self.neighbours)
+        if !self.topology.wrap_horizontal && !self.topology.wrap_vertical {
+            return settings.find_matches(&self.board, &self.lines, &self.neighbours);
+        }
+        let lines: Vec<Cow<[usize]>> = self
+            .vertical_lines()
+            .iter()
+            .map(|line| self.wrap_line(line, self.topology.wrap_vertical))
+            .chain(
+                self.horizontal_lines()
+                    .iter()
+                    .map(|line| self.wrap_line(line, self.topology.wrap_horizontal)),
+            )
+            .collect();
+        settings.find_matches(&self.board, &lines, &self.neighbours)
+    }
+
+    /// Rotates a wrapped line to start at the beginning of a group, so
+    /// matches can go across the seam while every cell is still visited once
+    ///
+    /// Cuts between gems that don't match are preferred, as no group can
+    /// cross them. When wildcards or multi-color gems connect every pair of
+    /// neighbours, the line starts where the narrowed color of a group
+    /// changes instead
+    fn wrap_line<'a>(&self, line: &'a [usize], wrap: bool) -> Cow<'a, [usize]> {
+        let len = line.len();
+        if !wrap || len < 2 {
+            return Cow::Borrowed(line);
+        }
+        let color = |i: usize| self.board[line[i % len]].color();
+        let start = (0..len)
+            .find(|&i| !color(i + len - 1).matches(&color(i)))
+            .or_else(|| {
+                let first = (0..len).find(|&i| color(i).can_start_match())?;
+                let mut group = color(first);
+                (first + 1..first + len).find_map(|i| {
+                    let gem = color(i);
+                    if group.matches(&gem) {
+                        group = group.narrow(&gem);
+                        None
+                    } else {
+                        Some(i % len)
+                    }
+                })
+            });
+        match start {
+            Some(start) if start > 0 => Cow::Owned(
+                line[start..]
+                    .iter()
+                    .chain(&line[..start])
+                    .copied()
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(line),
+        }
     }
 
     /// Returns the shortest path to move the gem between two positions
+    ///
+    /// Paths go through the wrapped board edges when that is shorter. Portals
+    /// are only taken when moving directly between the cells they connect,
+    /// and paths only go through the top and bottom edges when none of the
+    /// columns they pass has a portal, as those columns continue into other
+    /// ones instead of wrapping around
     pub fn move_gem(
         &mut self,
         from: usize,
         to: usize,
         strategy: GridMoveStrategy,
     ) -> impl Iterator<Item = usize> {
+        let [width, height] = self.shape.as_array();
         let [x_from, y_from] = self.shape.delinearize(from);
         let [x_to, y_to] = self.shape.delinearize(to);
-        let dx = ((x_to as isize) - (x_from as isize)).clamp(-1, 1);
-        let dy =
-            ((y_to as isize) - (y_from as isize)).clamp(-1, 1) * self.shape.as_array()[0] as isize;
-        let diagonal = match strategy {
-            GridMoveStrategy::VerticalFirst => dy,
-            GridMoveStrategy::HorizontalFirst => dx,
-            GridMoveStrategy::Diagonals => dy + dx,
-        };
+        let jump = (from != to && self.neighbours[from].contains(&to)).then_some(to);
+        let dx = step_direction(x_from, x_to, width, self.topology.wrap_horizontal);
+        let mut columns = std::iter::successors(Some(x_from), |&x| {
+            (x != x_to).then(|| (x as isize + dx).rem_euclid(width as isize) as usize)
+        });
+        let wrap_vertical =
+            self.topology.wrap_vertical && columns.all(|x| !self.topology.has_portal(x));
 
         GridMoveIter {
             shape: self.shape.clone(),
-            from: from as isize,
-            to: to as isize,
+            pos: if jump.is_some() {
+                [x_to, y_to]
+            } else {
+                [x_from, y_from]
+            },
+            target: [x_to, y_to],
             dx,
-            dy,
-            diagonal,
+            dy: step_direction(y_from, y_to, height, wrap_vertical),
+            strategy,
+            jump,
         }
     }
 
@@ -121,6 +323,41 @@ impl<T: BoardGem> RectBoard<T> {
     }
 }
 
+/// Links consecutive cells of the lines, optionally connecting line ends
+fn link_line_cells(
+    lines: &[Vec<usize>],
+    wrap: bool,
+    prev: &mut [Option<usize>],
+    next: &mut [Option<usize>],
+) {
+    for line in lines {
+        for (&a, &b) in line.iter().zip(line.iter().skip(1)) {
+            next[a] = Some(b);
+            prev[b] = Some(a);
+        }
+        if let (true, Some(&first), Some(&last)) = (wrap, line.first(), line.last()) {
+            next[last] = Some(first);
+            prev[first] = Some(last);
+        }
+    }
+}
+
+/// Returns the direction of the shortest step from one coordinate to another
+fn step_direction(from: usize, to: usize, size: usize, wrap: bool) -> isize {
+    let direct = (to as isize - from as isize).clamp(-1, 1);
+    if !wrap {
+        return direct;
+    }
+    let forward = (to + size - from) % size;
+    if forward == 0 {
+        0
+    } else if forward * 2 > size {
+        -1
+    } else {
+        1
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum GridMoveStrategy {
     VerticalFirst,
@@ -131,38 +368,52 @@ pub enum GridMoveStrategy {
 #[derive(Clone)]
 struct GridMoveIter {
     shape: RuntimeShape<usize, 2>,
-    from: isize,
-    to: isize,
+    pos: [usize; 2],
+    target: [usize; 2],
     dx: isize,
     dy: isize,
-    diagonal: isize,
+    strategy: GridMoveStrategy,
+    /// Direct move into a non-adjacent neighbour, like through a portal
+    jump: Option<usize>,
+}
+
+impl GridMoveIter {
+    fn step(&self, value: usize, delta: isize, axis: usize) -> usize {
+        let size = self.shape.as_array()[axis] as isize;
+        (value as isize + delta).rem_euclid(size) as usize
+    }
 }
 
 impl Iterator for GridMoveIter {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.from == self.to {
-            return None;
+        if let Some(jump) = self.jump.take() {
+            return Some(jump);
         }
 
-        let [x_from, y_from] = self.shape.delinearize(self.from as usize);
-        let [x_to, y_to] = self.shape.delinearize(self.to as usize);
-        match (x_from == x_to, y_from == y_to) {
-            (false, false) => {
-                self.from += self.diagonal;
-            }
-            (true, false) => {
-                self.from += self.dy;
-            }
-            (false, true) => {
-                self.from += self.dx;
-            }
+        let [x, y] = self.pos;
+        let [x_to, y_to] = self.target;
+        let (move_x, move_y) = match (x == x_to, y == y_to) {
+            (false, false) => match self.strategy {
+                GridMoveStrategy::VerticalFirst => (false, true),
+                GridMoveStrategy::HorizontalFirst => (true, false),
+                GridMoveStrategy::Diagonals => (true, true),
+            },
+            (true, false) => (false, true),
+            (false, true) => (true, false),
             (true, true) => {
                 return None;
             }
+        };
+
+        if move_x {
+            self.pos[0] = self.step(x, self.dx, 0);
+        }
+        if move_y {
+            self.pos[1] = self.step(y, self.dy, 1);
         }
 
-        Some(self.from as usize)
+        Some(self.shape.linearize(self.pos))
     }
 }
@@ -2,7 +2,10 @@ use crate::char_board::{
     board_from_str, display_board, pretty_print_board, visualise_and_apply_gravity,
     visualize_and_apply_matches, CharBoard, CharGem,
 };
-use crate::line::LineMatcherSettings;
+use crate::diff::BoardMirror;
+use crate::history::{BoardHistory, BoardOperation};
+use crate::line::{LineMatcherSettings, WildcardPolicy};
+use crate::rect_board::{GridMoveStrategy, RectTopology};
 use crate::refilling::{GravityRefill, GravityRefillAction};
 use crate::MatchColor;
 use insta::assert_snapshot;
@@ -22,6 +25,10 @@ struct MatchSettings {
     line_size: Option<usize>,
     min_group_size: Option<usize>,
     merge_neighbours: Option<bool>,
+    wildcard_policy: Option<String>,
+    wrap_horizontal: Option<bool>,
+    wrap_vertical: Option<bool>,
+    portals: Option<Vec<(usize, usize)>>,
 }
 
 fn check_path(prefix: &str, path: impl AsRef<Path>, gravity: bool) {
@@ -30,6 +37,7 @@ fn check_path(prefix: &str, path: impl AsRef<Path>, gravity: bool) {
     let mut lines = test.lines().peekable();
     let name = lines.next().expect("Should have name").trim();
     let mut settings_variations: Vec<(&'static str, S)> = vec![];
+    let mut topology = RectTopology::default();
     if let Some(peek) = lines.peek() {
         if peek.starts_with('{') {
             let mut match_settings = S::common_match3();
@@ -41,6 +49,20 @@ fn check_path(prefix: &str, path: impl AsRef<Path>, gravity: bool) {
             match_settings.merge_neighbours = settings
                 .merge_neighbours
                 .unwrap_or(match_settings.merge_neighbours);
+            if let Some(policy) = &settings.wildcard_policy {
+                match_settings.wildcard_policy = match policy.as_str() {
+                    "join_both" => WildcardPolicy::JoinBoth,
+                    "prefer_first" => WildcardPolicy::PreferFirst,
+                    "prefer_longest" => WildcardPolicy::PreferLongest,
+                    _ => panic!("Unknown wildcard policy: {policy}"),
+                };
+            }
+
+            topology.wrap_horizontal = settings.wrap_horizontal.unwrap_or_default();
+            topology.wrap_vertical = settings.wrap_vertical.unwrap_or_default();
+            for (from, to) in settings.portals.unwrap_or_default() {
+                topology = topology.with_portal(from, to);
+            }
 
             if settings.merge_neighbours.is_none() {
                 match_settings.merge_neighbours = false;
@@ -69,6 +91,7 @@ fn check_path(prefix: &str, path: impl AsRef<Path>, gravity: bool) {
         panic!("Board is empty")
     }
     let board = board_from_str(&lines.join("\n"));
+    let board = CharBoard::with_topology(board.width(), board.height(), board.board, topology);
 
     let variants = settings_variations
         .into_iter()
@@ -121,6 +144,252 @@ fn gravity_line3_file_tests(#[files("src/cases/gravity/*.txt")] path: PathBuf) {
     check_path("sizing", path, true);
 }
 
+#[rstest]
+fn topology_line3_file_tests(#[files("src/cases/topology/*.txt")] path: PathBuf) {
+    check_path("topology", path, true);
+}
+
+#[test]
+fn move_gem_wrapped() {
+    let mut board =
+        CharBoard::with_topology(5, 3, vec![CharGem('r'); 15], RectTopology::toroidal());
+    let mut path = |from, to| {
+        board
+            .move_gem(from, to, GridMoveStrategy::Diagonals)
+            .collect_vec()
+    };
+
+    assert_eq!(path(0, 4), vec![4]);
+    assert_eq!(path(0, 3), vec![4, 3]);
+    assert_eq!(path(0, 10), vec![10]);
+    assert_eq!(path(0, 14), vec![14]);
+    assert_eq!(path(0, 2), vec![1, 2]);
+}
+
+#[test]
+fn move_gem_through_portal() {
+    let board = CharBoard::from_element(2, 3, 'r');
+    let mut portal = CharBoard::with_topology(
+        2,
+        3,
+        board.board.clone(),
+        RectTopology::default().with_portal(0, 1),
+    );
+
+    assert_eq!(
+        portal
+            .move_gem(4, 1, GridMoveStrategy::Diagonals)
+            .collect_vec(),
+        vec![1]
+    );
+    assert_eq!(
+        board
+            .clone()
+            .move_gem(4, 1, GridMoveStrategy::Diagonals)
+            .collect_vec(),
+        vec![3, 1]
+    );
+}
+
+#[test]
+fn move_gem_wrapped_with_portal() {
+    let mut board = CharBoard::with_topology(
+        3,
+        5,
+        vec![CharGem('r'); 15],
+        RectTopology::default()
+            .with_wrap_vertical(true)
+            .with_portal(0, 1),
+    );
+    let mut path = |from, to| {
+        board
+            .move_gem(from, to, GridMoveStrategy::VerticalFirst)
+            .collect_vec()
+    };
+
+    // Columns without portals still wrap around
+    assert_eq!(path(2, 14), vec![14]);
+    // Columns with portals don't
+    assert_eq!(path(0, 12), vec![3, 6, 9, 12]);
+    assert_eq!(path(2, 12), vec![5, 8, 11, 14, 13, 12]);
+}
+
+#[test]
+fn history_undo_redo() {
+    let board = board_from_str("rgb\ngbr\nbgr");
+    let initial = display_board(&board, false);
+    let mut history = BoardHistory::new(board);
+
+    history.begin_turn();
+    assert_eq!(
+        history.move_gem(0, 2, GridMoveStrategy::Diagonals),
+        vec![1, 2]
+    );
+    let moved = display_board(history.board(), false);
+    assert_eq!(moved, "gbr\ngbr\nbgr");
+
+    history.begin_turn();
+    let settings = S::common_match3();
+    let matches = history.board().find_matches_linear(&settings);
+    history.remove_matched(&matches, || CharGem(' '));
+    let actions = GravityRefill::refill(&history.board().board, history.board().gravity_lines());
+    for action in &actions {
+        history.apply_refill(action, |_| CharGem('#'));
+    }
+    let refilled = display_board(history.board(), false);
+    assert_ne!(refilled, moved);
+
+    assert!(history.undo_step());
+    assert!(history.redo_step());
+    assert_eq!(display_board(history.board(), false), refilled);
+
+    assert!(history.undo_turn());
+    assert_eq!(display_board(history.board(), false), moved);
+    assert!(history.undo_turn());
+    assert_eq!(display_board(history.board(), false), initial);
+    assert!(!history.undo_turn());
+
+    assert!(history.redo_turn());
+    assert_eq!(display_board(history.board(), false), moved);
+    assert!(history.redo_turn());
+    assert_eq!(display_board(history.board(), false), refilled);
+    assert!(!history.redo_step());
+
+    // Recording after an undo discards the redo tail
+    history.undo_turn();
+    history.swap(0, 1);
+    assert!(!history.can_redo());
+    assert!(!history.redo_turn());
+}
+
+#[test]
+fn history_begin_turn_keeps_redo() {
+    let mut history = BoardHistory::new(board_from_str("rgb\ngbr\nbgr"));
+    history.begin_turn();
+    history.swap(0, 1);
+    history.swap(1, 2);
+    let swapped = display_board(history.board(), false);
+
+    assert!(history.undo_turn());
+    history.begin_turn();
+    assert!(history.can_redo());
+    assert!(history.redo_turn());
+    assert_eq!(display_board(history.board(), false), swapped);
+
+    // Starting a turn in the middle of an undone one splits it
+    assert!(history.undo_turn());
+    assert!(history.redo_step());
+    history.begin_turn();
+    assert!(history.undo_turn());
+    assert_eq!(history.cursor(), 0);
+    assert!(history.redo_turn());
+    assert_eq!(history.cursor(), 1);
+    assert!(history.redo_turn());
+    assert_eq!(display_board(history.board(), false), swapped);
+}
+
+#[test]
+fn board_diff_and_patch() {
+    let from = board_from_str("rgb\ngbr\nbgr");
+    let to = board_from_str("rgb\ng*r\nbg-");
+    let diff = from.diff(&to);
+    assert_eq!(
+        diff.iter()
+            .filter(|op| matches!(op, BoardOperation::Replace { .. }))
+            .count(),
+        2
+    );
+
+    let mut patched = from.clone();
+    patched.patch(&diff);
+    assert_eq!(patched.board, to.board);
+
+    let reverted = diff.iter().rev().map(|op| op.inverse()).collect_vec();
+    patched.patch(&reverted);
+    assert_eq!(patched.board, from.board);
+    assert!(from.diff(&from).is_empty());
+}
+
+#[test]
+fn board_equivalence() {
+    let board = board_from_str("rgb*\ngbr-\nbgrr");
+    let permuted = board_from_str("bpr*\nprb-\nrpbb");
+    let mirrored = board.mirrored(BoardMirror::Horizontal);
+    let upside_down = board.mirrored(BoardMirror::Vertical);
+    let different = board_from_str("rgb*\ngbr-\nbgrg");
+    let wildcard_moved = board_from_str("rgbr\ngbr-\nbgr*");
+
+    for equivalent in [
+        &permuted,
+        &mirrored,
+        &permuted.mirrored(BoardMirror::Horizontal),
+    ] {
+        assert!(board.is_equivalent(equivalent));
+        assert_eq!(board.canonical_hash(), equivalent.canonical_hash());
+    }
+    // Gravity makes upside-down boards play differently
+    for other in [&different, &wildcard_moved, &upside_down] {
+        assert!(!board.is_equivalent(other));
+        assert_ne!(board.canonical_hash(), other.canonical_hash());
+    }
+}
+
+#[test]
+fn board_equivalence_with_color_rules() {
+    // Heart gems match in pairs, so they can't be swapped with other colors
+    let hearts = board_from_str("hhr\nggb\nbrg");
+    let swapped = board_from_str("rrh\nggb\nbhg");
+    assert!(!hearts.is_equivalent(&swapped));
+    assert_ne!(hearts.canonical_hash(), swapped.canonical_hash());
+    let permuted = board_from_str("hhg\nrrb\nbgr");
+    assert!(hearts.is_equivalent(&permuted));
+    assert_eq!(hearts.canonical_hash(), permuted.canonical_hash());
+
+    // Violet gems match red, but not green
+    let red = board_from_str("vrr\nggb\nbgg");
+    let green = board_from_str("vgg\nrrb\nbrr");
+    assert!(!red.is_equivalent(&green));
+    assert_ne!(red.canonical_hash(), green.canonical_hash());
+    let permuted = board_from_str("vrr\nppb\nbpp");
+    assert!(red.is_equivalent(&permuted));
+    assert_eq!(red.canonical_hash(), permuted.canonical_hash());
+}
+
+#[test]
+fn board_equivalence_with_portals() {
+    let colors = board_from_str("rgb\ngbr\nbgg");
+    let portal = |board: &CharBoard, from, to| {
+        CharBoard::with_topology(
+            3,
+            3,
+            board.board.clone(),
+            RectTopology::default().with_portal(from, to),
+        )
+    };
+    let board = portal(&colors, 0, 1);
+
+    // Mirroring only the colors leads gems through a different portal
+    let flipped = portal(&colors.mirrored(BoardMirror::Horizontal), 0, 1);
+    assert!(!board.is_equivalent(&flipped));
+    assert_ne!(board.canonical_hash(), flipped.canonical_hash());
+
+    for mirror in BoardMirror::ALL {
+        let mirrored = board.mirrored(mirror);
+        let preserved = BoardMirror::GRAVITY_PRESERVING.contains(&mirror);
+        assert_eq!(board.is_equivalent(&mirrored), preserved, "{mirror:?}");
+        assert_eq!(
+            board.canonical_hash() == mirrored.canonical_hash(),
+            preserved,
+            "{mirror:?}"
+        );
+    }
+    assert_eq!(
+        board.mirrored(BoardMirror::Both).topology.portals,
+        portal(&colors, 1, 2).topology.portals
+    );
+    assert!(!board.is_equivalent(&colors));
+}
+
 fn prop_board(size: usize) -> impl Strategy<Value = CharBoard> {
     (3..size, 3..size)
         .prop_flat_map(|(width, height)| {
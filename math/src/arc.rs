@@ -1,63 +1,92 @@
-use crate::lib_ext::FloatLibmExt;
-use glam::{vec2, Vec2};
+use crate::scalar::Real;
+use glam::Vec2;
 
 // https://math.stackexchange.com/questions/482751/how-do-i-move-through-an-arc-between-two-specific-points
 // https://www.desmos.com/geometry/aofv2koj0k
 /// Returns the center and radius of an arc that goes through `from` and `to` with a given `bulge`
 pub fn arc_center_radius(from: Vec2, to: Vec2, bulge: f32, flip: bool) -> (Vec2, f32) {
-    let distance = from.distance(to);
+    let (center, radius) = center_radius(from.into(), to.into(), bulge, flip);
+    (center.into(), radius)
+}
+
+/// Returns the starting and ending angles of an arc that starts from `from` with a given `bulge` and radius
+pub fn arc_angles(
+    center: Vec2,
+    radius: f32,
+    bulge: f32,
+    from: Vec2,
+    to: Vec2,
+    flip: bool,
+) -> (f32, f32) {
+    angles(center.into(), radius, bulge, from.into(), to.into(), flip)
+}
+
+/// Generic version of [arc_center_radius], working with `[x, y]` points
+pub fn center_radius<S: Real>(from: [S; 2], to: [S; 2], bulge: S, flip: bool) -> ([S; 2], S) {
+    let two = S::from_int(2);
+    let distance = length([to[0] - from[0], to[1] - from[1]]);
 
-    let s = bulge * distance / 2.0;
+    let s = bulge * distance / two;
 
-    let radius = ((distance / 2.0).powi_m(2) + s.powi_m(2)) / (2.0 * s);
+    let radius = ((distance / two).powi(2) + s.powi(2)) / (two * s);
 
-    let arc = 4.0 * bulge.atan_m();
+    let arc = S::from_int(4) * bulge.atan();
 
-    let c_x = radius * (arc / 2.0 - core::f32::consts::FRAC_PI_2).cos_m();
-    let mut c_y = -radius * (arc / 2.0 - core::f32::consts::FRAC_PI_2).sin_m();
+    let c_x = radius * (arc / two - S::FRAC_PI_2).cos();
+    let mut c_y = -radius * (arc / two - S::FRAC_PI_2).sin();
     if flip {
         c_y = -c_y;
     }
 
-    let center = normalize_or_right(to - from).rotate(vec2(c_x, c_y)) + from;
+    let [dx, dy] = normalize_or_right([to[0] - from[0], to[1] - from[1]]);
+    // Rotates the center offset by the direction
+    let center = [dx * c_x - dy * c_y + from[0], dy * c_x + dx * c_y + from[1]];
     (center, radius)
 }
 
-/// Returns the starting and ending angles of an arc that starts from `from` with a given `bulge` and radius
-pub fn arc_angles(
-    center: Vec2,
-    radius: f32,
-    bulge: f32,
-    from: Vec2,
-    to: Vec2,
+/// Generic version of [arc_angles], working with `[x, y]` points
+pub fn angles<S: Real>(
+    center: [S; 2],
+    radius: S,
+    bulge: S,
+    from: [S; 2],
+    to: [S; 2],
     flip: bool,
-) -> (f32, f32) {
-    let arc = 4.0 * bulge.atan_m();
+) -> (S, S) {
+    let arc = S::from_int(4) * bulge.atan();
     let anchor = if flip { to } else { from };
 
-    let starting_angle = if center.y < anchor.y {
-        ((anchor.x - center.x) / radius).clamp(-1.0, 1.0).acos_m()
+    let cos = ((anchor[0] - center[0]) / radius).clamp(-S::ONE, S::ONE);
+    let starting_angle = if center[1] < anchor[1] {
+        cos.acos()
     } else {
-        ((anchor.x - center.x) / radius).clamp(-1.0, 1.0).asin_m() - core::f32::consts::FRAC_PI_2
+        cos.asin() - S::FRAC_PI_2
     };
     let end_angle = starting_angle + arc;
     (starting_angle, end_angle)
 }
 
-fn normalize_or_right(vec: Vec2) -> Vec2 {
-    let rcp = vec.length_recip();
+fn length<S: Real>(vec: [S; 2]) -> S {
+    (vec[0] * vec[0] + vec[1] * vec[1]).sqrt()
+}
 
-    if rcp.is_finite() && rcp > 0.0 {
-        vec * rcp
-    } else {
-        Vec2::X
+fn normalize_or_right<S: Real>(vec: [S; 2]) -> [S; 2] {
+    let length = length(vec);
+
+    if length > S::ZERO {
+        let rcp = S::ONE / length;
+        if rcp > S::ZERO {
+            return [vec[0] * rcp, vec[1] * rcp];
+        }
     }
+    [S::ONE, S::ZERO]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_relative_eq;
+    use glam::vec2;
     use proptest::{prop_assume, proptest};
 
     // https://www.desmos.com/geometry/d0kujz75r0
@@ -108,4 +137,30 @@ mod tests {
             assert_relative_eq!(perp_center.distance(center), radius - (bulge * from.distance(to) / 2.0), epsilon = epsilon);
         }
     }
+
+    #[cfg(feature = "fixed")]
+    #[test]
+    fn fixed_point_arcs() {
+        use crate::fixed::Fixed;
+
+        let from = [Fixed::from_f32(0.61), Fixed::from_f32(1.23)];
+        let to = [Fixed::from_f32(2.283), Fixed::from_f32(1.67)];
+        let bulge = Fixed::from_f32(0.673);
+        let (center, radius) = center_radius(from, to, bulge, false);
+        assert_relative_eq!(center[0].to_f32(), 1.3570827, epsilon = 1e-5);
+        assert_relative_eq!(center[1].to_f32(), 1.7899889, epsilon = 1e-5);
+        assert_relative_eq!(radius.to_f32(), 0.93365955, epsilon = 1e-5);
+
+        let (start, end) = angles(center, radius, bulge, from, to, false);
+        let (float_start, float_end) = arc_angles(
+            vec2(1.3570827, 1.7899889),
+            0.93365955,
+            0.673,
+            vec2(0.61, 1.23),
+            vec2(2.283, 1.67),
+            false,
+        );
+        assert_relative_eq!(start.to_f32(), float_start, epsilon = 1e-4);
+        assert_relative_eq!(end.to_f32(), float_end, epsilon = 1e-4);
+    }
 }
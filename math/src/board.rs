@@ -1,5 +1,7 @@
+use crate::scalar::Real;
 use core::fmt::{Debug, Formatter};
 use core::hash::{Hash, Hasher};
+use glam::{vec2, Affine2};
 use glamour::{point, size, Box2, Point2, Rect, Size2, Unit, Vector2};
 use ndshape::{RuntimeShape, Shape};
 
@@ -47,32 +49,455 @@ impl Hash for CellIndex {
     }
 }
 
+/// Way of measuring distance between cells
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GridMetric {
+    /// Number of orthogonal steps
+    Manhattan,
+    /// Number of steps when diagonal steps are allowed
+    Chebyshev,
+    /// Straight line distance between cell centers
+    Euclidean,
+    /// Number of steps when diagonal steps are allowed, but cost `diagonal`
+    /// orthogonal steps each
+    Diagonal { diagonal: f32 },
+}
+
+impl GridMetric {
+    /// Diagonal metric with diagonal steps costing the same as in
+    /// euclidean space
+    pub const OCTILE: GridMetric = GridMetric::Diagonal {
+        diagonal: core::f32::consts::SQRT_2,
+    };
+
+    /// Returns the distance for the given difference of cell coordinates
+    pub fn distance(self, dx: usize, dy: usize) -> f32 {
+        let (dx, dy) = (dx as f32, dy as f32);
+        match self {
+            GridMetric::Manhattan => dx + dy,
+            GridMetric::Chebyshev => dx.max(dy),
+            GridMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            GridMetric::Diagonal { diagonal } => {
+                let (short, long) = (dx.min(dy), dx.max(dy));
+                short * diagonal + (long - short)
+            }
+        }
+    }
+
+    /// Returns the ring the cell at the given difference of coordinates
+    /// belongs to, so that every cell belongs to exactly one ring
+    ///
+    /// For metrics with fractional distances, ring `n` holds cells with
+    /// distance above `n - 1` and up to `n`
+    pub fn ring(self, dx: usize, dy: usize) -> usize {
+        // Tolerance for rounding errors of fractional metrics
+        (self.distance(dx, dy) - 1e-4).ceil().max(0.0) as usize
+    }
+
+    /// Returns the largest difference of coordinates along a single axis
+    /// between cells that are within `distance` from each other
+    pub fn reach(self, distance: f32) -> usize {
+        // Cheapest cost of a step along the longer axis
+        let step = match self {
+            GridMetric::Diagonal { diagonal } => diagonal.min(1.0),
+            _ => 1.0,
+        };
+        if step.is_nan() || step <= 0.0 {
+            return usize::MAX;
+        }
+        // Same tolerance as in [GridMetric::ring]
+        ((distance.max(0.0) + 1e-4) / step).floor() as usize
+    }
+}
+
+/// Cell layout of a grid in view space, generic over the scalar type
+///
+/// [GridMath] uses it with [f32] coordinates, while gameplay code can get a
+/// copy working with deterministic scalars through [GridMath::geometry].
+/// Points are given as `[x, y]`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GridGeometry<S: Real> {
+    origin: [S; 2],
+    cell_size: [S; 2],
+    cols: usize,
+    rows: usize,
+    swap_y: bool,
+}
+
+impl<S: Real> GridGeometry<S> {
+    pub fn new(origin: [S; 2], size: [S; 2], cols: usize, rows: usize, swap_y: bool) -> Self {
+        Self {
+            origin,
+            cell_size: [
+                size[0] / S::from_int(cols as i32),
+                size[1] / S::from_int(rows as i32),
+            ],
+            cols,
+            rows,
+            swap_y,
+        }
+    }
+
+    pub fn cell_size(&self) -> [S; 2] {
+        self.cell_size
+    }
+
+    fn linearize(&self, x: usize, y: usize) -> CellIndex {
+        let y = if self.swap_y { self.rows - y - 1 } else { y };
+        CellIndex(x + y * self.cols)
+    }
+
+    fn delinearize(&self, index: CellIndex) -> (usize, usize) {
+        let (x, y) = (index.0 % self.cols, index.0 / self.cols);
+        let y = if self.swap_y { self.rows - y - 1 } else { y };
+        (x, y)
+    }
+
+    /// Returns fractional cell coordinates of the view position, where cell
+    /// `(x, y)` spans from `x` to `x + 1` and from `y` to `y + 1`
+    pub fn cell_coords(&self, pos: [S; 2]) -> [S; 2] {
+        [
+            (pos[0] - self.origin[0]) / self.cell_size[0],
+            (pos[1] - self.origin[1]) / self.cell_size[1],
+        ]
+    }
+
+    /// Returns the cell containing the view position, or [None] if the
+    /// position is outside of the grid
+    pub fn view_to_index(&self, pos: [S; 2]) -> Option<CellIndex> {
+        let [x, y] = self.cell_coords(pos);
+        let in_bounds = x >= S::ZERO
+            && x < S::from_int(self.cols as i32)
+            && y >= S::ZERO
+            && y < S::from_int(self.rows as i32);
+        if !in_bounds {
+            return None;
+        }
+        let x = (x.floor_to_int() as usize).min(self.cols - 1);
+        let y = (y.floor_to_int() as usize).min(self.rows - 1);
+        Some(self.linearize(x, y))
+    }
+
+    /// Returns the cell closest to the view position, clamping positions
+    /// outside of the grid to the border cells
+    pub fn view_to_index_clamped(&self, pos: [S; 2]) -> CellIndex {
+        let [x, y] = self.cell_coords(pos);
+        let x = (x.floor_to_int().max(0) as usize).min(self.cols - 1);
+        let y = (y.floor_to_int().max(0) as usize).min(self.rows - 1);
+        self.linearize(x, y)
+    }
+
+    /// Returns the minimal corner of a cell
+    pub fn origin_at_index(&self, index: CellIndex) -> [S; 2] {
+        let (x, y) = self.delinearize(index);
+        [
+            self.origin[0] + S::from_int(x as i32) * self.cell_size[0],
+            self.origin[1] + S::from_int(y as i32) * self.cell_size[1],
+        ]
+    }
+
+    /// Returns the center of a cell
+    pub fn center_at_index(&self, index: CellIndex) -> [S; 2] {
+        let (x, y) = self.delinearize(index);
+        let half = S::ONE / S::from_int(2);
+        [
+            self.origin[0] + (S::from_int(x as i32) + half) * self.cell_size[0],
+            self.origin[1] + (S::from_int(y as i32) + half) * self.cell_size[1],
+        ]
+    }
+
+    /// Returns the offset of the view position from the center of a cell,
+    /// in cell units
+    pub fn offset_from_center(&self, pos: [S; 2], index: CellIndex) -> [S; 2] {
+        let center = self.center_at_index(index);
+        [
+            (pos[0] - center[0]) / self.cell_size[0],
+            (pos[1] - center[1]) / self.cell_size[1],
+        ]
+    }
+
+    /// Returns the distance between the view position and the center of a
+    /// cell, in cell units
+    pub fn distance_from_center(&self, pos: [S; 2], index: CellIndex) -> S {
+        let [dx, dy] = self.offset_from_center(pos, index);
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /// Checks whenever the view position is within `radius` cells from the
+    /// center of a cell
+    pub fn hits_cell(&self, pos: [S; 2], index: CellIndex, radius: S) -> bool {
+        let [dx, dy] = self.offset_from_center(pos, index);
+        dx * dx + dy * dy <= radius * radius
+    }
+
+    /// Returns the squared euclidean distance between two cells
+    pub fn distance2(&self, a: CellIndex, b: CellIndex) -> S {
+        let (ax, ay) = self.delinearize(a);
+        let (bx, by) = self.delinearize(b);
+        let dx = S::from_int(ax as i32) - S::from_int(bx as i32);
+        let dy = S::from_int(ay as i32) - S::from_int(by as i32);
+        dx * dx + dy * dy
+    }
+
+    /// Returns the euclidean distance between two cells
+    pub fn distance(&self, a: CellIndex, b: CellIndex) -> S {
+        self.distance2(a, b).sqrt()
+    }
+
+    /// Returns cells crossed by the segment between two view positions, in
+    /// order from `from` to `to`
+    ///
+    /// Consecutive cells are orthogonal neighbours, unless diagonal steps are
+    /// enabled, so fast mouse movement can be turned into a sequence of
+    /// steps. Parts of the segment outside of the grid are skipped
+    pub fn segment_cells(&self, from: [S; 2], to: [S; 2]) -> SegmentCells<S> {
+        SegmentCells::new(*self, self.cell_coords(from), self.cell_coords(to))
+    }
+}
+
+/// Iterator over cells crossed by a segment, see [GridGeometry::segment_cells]
+///
+/// Walks the grid with a DDA, visiting every cell the segment passes
+/// through. When the segment passes exactly through a cell corner, it goes
+/// through the horizontal neighbour first, or steps diagonally if diagonals
+/// are enabled
+#[derive(Debug, Clone)]
+pub struct SegmentCells<S: Real> {
+    geometry: GridGeometry<S>,
+    /// Segment ends in cell coordinates
+    from: [S; 2],
+    to: [S; 2],
+    cell: [i64; 2],
+    step: [i64; 2],
+    /// Segment progress at the next cell border on each axis
+    next_border: [Option<S>; 2],
+    /// Segment progress between cell borders on each axis
+    border_delta: [Option<S>; 2],
+    /// Steps left on each axis until the last cell
+    remaining: [u64; 2],
+    finished: bool,
+    diagonals: bool,
+    hit_radius: Option<S>,
+}
+
+impl<S: Real> SegmentCells<S> {
+    fn new(geometry: GridGeometry<S>, from: [S; 2], to: [S; 2]) -> Self {
+        let mut cell = [0; 2];
+        let mut step = [0; 2];
+        let mut next_border = [None; 2];
+        let mut border_delta = [None; 2];
+        let mut remaining = [0; 2];
+        for axis in 0..2 {
+            cell[axis] = from[axis].floor_to_int();
+            let end = to[axis].floor_to_int();
+            remaining[axis] = cell[axis].abs_diff(end);
+            let delta = to[axis] - from[axis];
+            if delta > S::ZERO {
+                step[axis] = 1;
+                next_border[axis] = Some((from[axis].floor() + S::ONE - from[axis]) / delta);
+                border_delta[axis] = Some(S::ONE / delta);
+            } else if delta < S::ZERO {
+                step[axis] = -1;
+                next_border[axis] = Some((from[axis] - from[axis].floor()) / -delta);
+                border_delta[axis] = Some(S::ONE / -delta);
+            }
+        }
+        Self {
+            geometry,
+            from,
+            to,
+            cell,
+            step,
+            next_border,
+            border_delta,
+            remaining,
+            finished: false,
+            diagonals: false,
+            hit_radius: None,
+        }
+    }
+
+    /// Allows diagonal steps when the segment passes through a cell corner
+    pub fn with_diagonals(mut self, diagonals: bool) -> Self {
+        self.diagonals = diagonals;
+        self
+    }
+
+    /// Only yields cells whose center is within `radius` cells from the
+    /// segment, like [GridGeometry::hits_cell] does for single positions
+    pub fn with_hit_radius(mut self, radius: S) -> Self {
+        self.hit_radius = Some(radius);
+        self
+    }
+
+    fn step_axis(&mut self, axis: usize) {
+        self.cell[axis] += self.step[axis];
+        self.remaining[axis] -= 1;
+        if let (Some(border), Some(delta)) = (self.next_border[axis], self.border_delta[axis]) {
+            self.next_border[axis] = Some(border + delta);
+        }
+    }
+
+    /// Moves to the next cell, or finishes after the last one
+    fn advance(&mut self) {
+        match self.remaining {
+            [0, 0] => self.finished = true,
+            [_, 0] => self.step_axis(0),
+            [0, _] => self.step_axis(1),
+            _ => match self.next_border {
+                [Some(x), Some(y)] if x == y && self.diagonals => {
+                    self.step_axis(0);
+                    self.step_axis(1);
+                }
+                [Some(x), Some(y)] if y < x => self.step_axis(1),
+                [None, Some(_)] => self.step_axis(1),
+                _ => self.step_axis(0),
+            },
+        }
+    }
+
+    fn hits(&self, x: usize, y: usize) -> bool {
+        let Some(radius) = self.hit_radius else {
+            return true;
+        };
+        let half = S::ONE / S::from_int(2);
+        let center = [S::from_int(x as i32) + half, S::from_int(y as i32) + half];
+        let delta = [self.to[0] - self.from[0], self.to[1] - self.from[1]];
+        let length2 = delta[0] * delta[0] + delta[1] * delta[1];
+        // Closest point of the segment to the cell center
+        let t = if length2 > S::ZERO {
+            (((center[0] - self.from[0]) * delta[0] + (center[1] - self.from[1]) * delta[1])
+                / length2)
+                .clamp(S::ZERO, S::ONE)
+        } else {
+            S::ZERO
+        };
+        let dx = self.from[0] + delta[0] * t - center[0];
+        let dy = self.from[1] + delta[1] * t - center[1];
+        dx * dx + dy * dy <= radius * radius
+    }
+}
+
+impl<S: Real> Iterator for SegmentCells<S> {
+    type Item = CellIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let [x, y] = self.cell;
+            self.advance();
+            let in_bounds = x >= 0
+                && y >= 0
+                && (x as usize) < self.geometry.cols
+                && (y as usize) < self.geometry.rows;
+            if in_bounds && self.hits(x as usize, y as usize) {
+                return Some(self.geometry.linearize(x as usize, y as usize));
+            }
+        }
+        None
+    }
+}
+
 #[derive(Clone)]
 pub struct GridMath<ViewUnits: Unit<Scalar = f32>, const MICROCELLS: u64 = 1000> {
     rect: Rect<ViewUnits>,
     shape: RuntimeShape<usize, 2>,
     cols: usize,
     rows: usize,
-    cell_width: f32,
-    cell_height: f32,
+    geometry: GridGeometry<f32>,
     swap_y: bool,
+    /// Transform from board space, where the grid occupies `rect`, to view
+    /// space, along with its inverse
+    transform: Option<(Affine2, Affine2)>,
 }
 
 impl<ViewUnits: Unit<Scalar = f32>, const MICROCELLS: u64> GridMath<ViewUnits, MICROCELLS> {
     pub fn new(rect: Rect<ViewUnits>, cols: usize, rows: usize, swap_y: bool) -> Self {
-        let cell_width = rect.width() / cols as f32;
-        let cell_height = rect.height() / rows as f32;
         Self {
             rect,
             shape: RuntimeShape::<usize, 2>::new([cols, rows]),
             cols,
             rows,
-            cell_width,
-            cell_height,
+            geometry: GridGeometry::new(
+                [rect.origin.x, rect.origin.y],
+                [rect.width(), rect.height()],
+                cols,
+                rows,
+                swap_y,
+            ),
             swap_y,
+            transform: None,
         }
     }
 
+    /// Places the grid in view space with a transform, for effects like
+    /// camera pan, zoom or a rotated board
+    ///
+    /// The grid rect stays in board space, and the transform maps it to view
+    /// space. View positions passed to queries are mapped back, so hit tests
+    /// follow the transformed cells
+    ///
+    /// Transforms that can't be inverted are ignored, see
+    /// [GridGeometry::set_transform]
+    pub fn with_transform(mut self, transform: Affine2) -> Self {
+        self.set_transform(Some(transform));
+        self
+    }
+
+    /// Sets or clears the view transform
+    ///
+    /// Returns `false` and keeps the previous transform if the new one can't
+    /// be inverted, like one with zero scale, as view positions couldn't be
+    /// mapped back to the board
+    pub fn set_transform(&mut self, transform: Option<Affine2>) -> bool {
+        match transform {
+            Some(transform) => {
+                let inverse = transform.inverse();
+                if !transform.is_finite() || !inverse.is_finite() {
+                    return false;
+                }
+                self.transform = Some((transform, inverse));
+            }
+            None => self.transform = None,
+        }
+        true
+    }
+
+    pub fn transform(&self) -> Option<Affine2> {
+        self.transform.map(|(t, _)| t)
+    }
+
+    /// Maps a view position to board space
+    fn to_board(&self, pos: Vector2<ViewUnits>) -> [f32; 2] {
+        match self.transform {
+            Some((_, inverse)) => inverse.transform_point2(vec2(pos.x, pos.y)).into(),
+            None => [pos.x, pos.y],
+        }
+    }
+
+    /// Maps a board space position to view space
+    fn to_view(&self, pos: [f32; 2]) -> Point2<ViewUnits> {
+        let [x, y] = match self.transform {
+            Some((transform, _)) => transform.transform_point2(pos.into()).into(),
+            None => pos,
+        };
+        point!(x, y)
+    }
+
+    /// Returns the cell layout of the grid with the given scalar type, so
+    /// gameplay code can use deterministic math for the same grid
+    ///
+    /// The layout is in board space, ignoring the grid transform
+    pub fn geometry<S: Real>(&self) -> GridGeometry<S> {
+        let rect = &self.rect;
+        GridGeometry::new(
+            [S::from_f32(rect.origin.x), S::from_f32(rect.origin.y)],
+            [S::from_f32(rect.width()), S::from_f32(rect.height())],
+            self.cols,
+            self.rows,
+            self.swap_y,
+        )
+    }
+
     fn linearize(&self, x: usize, y: usize) -> usize {
         if self.swap_y {
             self.shape.linearize([x, self.rows - y - 1])
@@ -104,65 +529,205 @@ impl<ViewUnits: Unit<Scalar = f32>, const MICROCELLS: u64> GridMath<ViewUnits, M
 
     /// Returns the closest micro position to the given view position
     pub fn view_to_micropos(&self, pos: Vector2<ViewUnits>) -> GridMicroPos {
-        let x = ((pos.x - self.rect.origin.x) / self.cell_width * MICROCELLS as f32).floor() as u64;
-        let y =
-            ((pos.y - self.rect.origin.y) / self.cell_height * MICROCELLS as f32).floor() as u64;
-        point!(x, y)
+        let [x, y] = self.geometry.cell_coords(self.to_board(pos));
+        point!(
+            (x * MICROCELLS as f32).floor() as u64,
+            (y * MICROCELLS as f32).floor() as u64
+        )
+    }
+
+    /// Returns the cell containing the view position, or [None] if the
+    /// position is outside of the grid
+    pub fn view_to_index(&self, pos: Vector2<ViewUnits>) -> Option<CellIndex> {
+        self.geometry.view_to_index(self.to_board(pos))
+    }
+
+    /// Returns the cell closest to the view position, clamping positions
+    /// outside of the grid to the border cells
+    pub fn view_to_index_clamped(&self, pos: Vector2<ViewUnits>) -> CellIndex {
+        self.geometry.view_to_index_clamped(self.to_board(pos))
+    }
+
+    /// Returns the offset of the view position from the center of a cell,
+    /// in cell units
+    pub fn offset_from_center(&self, pos: Vector2<ViewUnits>, index: CellIndex) -> (f32, f32) {
+        let [dx, dy] = self.geometry.offset_from_center(self.to_board(pos), index);
+        (dx, dy)
+    }
+
+    /// Returns the distance between the view position and the center of a
+    /// cell, in cell units, so the border of the cell is 0.5 away
+    pub fn distance_from_center(&self, pos: Vector2<ViewUnits>, index: CellIndex) -> f32 {
+        self.geometry
+            .distance_from_center(self.to_board(pos), index)
+    }
+
+    /// Checks whenever the view position is within `radius` cells from the
+    /// center of a cell
+    ///
+    /// Radius above 0.5 makes circles of neighbouring cells overlap, while
+    /// radius below it leaves dead zones near cell borders
+    pub fn hits_cell(&self, pos: Vector2<ViewUnits>, index: CellIndex, radius: f32) -> bool {
+        self.geometry.hits_cell(self.to_board(pos), index, radius)
+    }
+
+    /// Returns cells crossed by the segment between two view positions, in
+    /// order, see [GridGeometry::segment_cells]
+    ///
+    /// Lets frontends turn one frame of mouse movement into a sequence of
+    /// cells, instead of only checking the cell under the cursor
+    pub fn segment_cells(
+        &self,
+        from: Vector2<ViewUnits>,
+        to: Vector2<ViewUnits>,
+    ) -> SegmentCells<f32> {
+        self.geometry
+            .segment_cells(self.to_board(from), self.to_board(to))
+    }
+
+    /// Returns the orthogonal neighbour of the cell containing the view
+    /// position, that the position is closest to
+    ///
+    /// Returns [None] if the position is outside of the grid, exactly at the
+    /// cell center, or if the neighbour would be outside of the grid
+    pub fn leaning_neighbour(&self, pos: Vector2<ViewUnits>) -> Option<CellIndex> {
+        let index = self.view_to_index(pos)?;
+        let (dx, dy) = self.offset_from_center(pos, index);
+        let (x, y) = self.delinearize(index.0);
+        let (x, y) = (x as isize, y as isize);
+        let (nx, ny) = if dx == 0.0 && dy == 0.0 {
+            return None;
+        } else if dx.abs() >= dy.abs() {
+            (x + dx.signum() as isize, y)
+        } else {
+            (x, y + dy.signum() as isize)
+        };
+        if nx < 0 || ny < 0 || nx as usize >= self.cols || ny as usize >= self.rows {
+            return None;
+        }
+        Some(CellIndex(self.linearize(nx as usize, ny as usize)))
     }
 
     /// Returns a rect for a cell in a grid
+    ///
+    /// With a rotating or skewing transform, returns the bounding rect of
+    /// the transformed cell, see [GridMath::corners_at_index]
     pub fn rect_at_index(&self, index: CellIndex) -> Rect<ViewUnits> {
-        let (x, y) = self.delinearize(index.0);
-        let minx = self.rect.origin.x + x as f32 * self.cell_width;
-        let miny = self.rect.origin.y + y as f32 * self.cell_height;
-        let maxx = minx + self.cell_width;
-        let maxy = miny + self.cell_height;
+        let corners = self.corners_at_index(index);
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for corner in &corners[1..] {
+            min = point!(min.x.min(corner.x), min.y.min(corner.y));
+            max = point!(max.x.max(corner.x), max.y.max(corner.y));
+        }
+
+        Box2::new((min.x, min.y), (max.x, max.y)).into()
+    }
 
-        Box2::new((minx, miny), (maxx, maxy)).into()
+    /// Returns the corners of a cell in view space, starting from the
+    /// minimal corner in board space and going clockwise in y-down space
+    pub fn corners_at_index(&self, index: CellIndex) -> [Point2<ViewUnits>; 4] {
+        let [minx, miny] = self.geometry.origin_at_index(index);
+        let maxx = minx + self.cell_width();
+        let maxy = miny + self.cell_height();
+        [[minx, miny], [maxx, miny], [maxx, maxy], [minx, maxy]].map(|pos| self.to_view(pos))
     }
 
     /// Returns the center of a cell in a grid
     pub fn center_at_index(&self, index: CellIndex) -> Point2<ViewUnits> {
-        let (x, y) = self.delinearize(index.0);
-        let pos_x = self.rect.origin.x + (x as f32 + 0.5) * self.cell_width;
-        let pos_y = self.rect.origin.y + (y as f32 + 0.5) * self.cell_height;
-        point!(pos_x, pos_y)
+        self.to_view(self.geometry.center_at_index(index))
     }
 
     /// Returns a single cell-sized rect with minx and miny at zero
+    ///
+    /// The size is in board space, ignoring the grid transform
     pub fn unit_cell(&self) -> Rect<ViewUnits> {
-        Rect::from_size(size!(self.cell_width, self.cell_height))
+        Rect::from_size(size!(self.cell_width(), self.cell_height()))
     }
 
     /// Returns the squared euclidean distance between two cells
     pub fn distance2(&self, a: CellIndex, b: CellIndex) -> f32 {
-        let (ax, ay) = self.delinearize(a.0);
-        let (bx, by) = self.delinearize(b.0);
-        let dx = ax as f32 - bx as f32;
-        let dy = ay as f32 - by as f32;
-        dx * dx + dy * dy
+        self.geometry.distance2(a, b)
     }
 
     /// Returns the euclidean distance between two cells
     pub fn distance(&self, a: CellIndex, b: CellIndex) -> f32 {
-        self.distance2(a, b).sqrt()
+        self.geometry.distance(a, b)
     }
 
-    /// Returns the grid (manhattan) distance between two cells
+    /// Returns the grid (chebyshev) distance between two cells, where
+    /// diagonal steps are allowed
+    ///
+    /// See [GridMath::metric_distance] for other metrics
     pub fn grid_distance(&self, a: CellIndex, b: CellIndex) -> usize {
+        let (dx, dy) = self.cell_delta(a, b);
+        dx.max(dy)
+    }
+
+    /// Returns the distance between two cells using the given metric
+    pub fn metric_distance(&self, a: CellIndex, b: CellIndex, metric: GridMetric) -> f32 {
+        let (dx, dy) = self.cell_delta(a, b);
+        metric.distance(dx, dy)
+    }
+
+    /// Returns all cells within `radius` from the cell, including itself,
+    /// in row order
+    pub fn cells_within(
+        &self,
+        index: CellIndex,
+        radius: f32,
+        metric: GridMetric,
+    ) -> impl Iterator<Item = CellIndex> + '_ {
+        self.cells_around(index, metric.reach(radius))
+            .filter(move |&(_, dx, dy)| metric.distance(dx, dy) <= radius + 1e-4)
+            .map(|(cell, _, _)| cell)
+    }
+
+    /// Returns all cells in the `radius`-th ring around the cell, in row
+    /// order, see [GridMetric::ring]
+    pub fn ring(
+        &self,
+        index: CellIndex,
+        radius: usize,
+        metric: GridMetric,
+    ) -> impl Iterator<Item = CellIndex> + '_ {
+        self.cells_around(index, metric.reach(radius as f32))
+            .filter(move |&(_, dx, dy)| metric.ring(dx, dy) == radius)
+            .map(|(cell, _, _)| cell)
+    }
+
+    /// Returns cells in a square of `reach` cells around the cell, along with
+    /// the difference of their coordinates
+    fn cells_around(
+        &self,
+        index: CellIndex,
+        reach: usize,
+    ) -> impl Iterator<Item = (CellIndex, usize, usize)> + '_ {
+        let (x, y) = self.delinearize(index.0);
+        let xs = x.saturating_sub(reach)..=x.saturating_add(reach).min(self.cols - 1);
+        let ys = y.saturating_sub(reach)..=y.saturating_add(reach).min(self.rows - 1);
+        ys.flat_map(move |cy| xs.clone().map(move |cx| (cx, cy)))
+            .map(move |(cx, cy)| {
+                (
+                    CellIndex(self.linearize(cx, cy)),
+                    cx.abs_diff(x),
+                    cy.abs_diff(y),
+                )
+            })
+    }
+
+    /// Returns absolute differences of cell coordinates
+    fn cell_delta(&self, a: CellIndex, b: CellIndex) -> (usize, usize) {
         let (ax, ay) = self.delinearize(a.0);
         let (bx, by) = self.delinearize(b.0);
-        let dx = (ax as isize - bx as isize).abs();
-        let dy = (ay as isize - by as isize).abs();
-        dx.max(dy) as usize
+        (ax.abs_diff(bx), ay.abs_diff(by))
     }
 
     pub fn cell_width(&self) -> f32 {
-        self.cell_width
+        self.geometry.cell_size()[0]
     }
 
     pub fn cell_height(&self) -> f32 {
-        self.cell_height
+        self.geometry.cell_size()[1]
     }
 
     pub fn shape(&self) -> &RuntimeShape<usize, 2> {
@@ -179,8 +744,401 @@ impl<ViewUnits: Unit<Scalar = f32>, const MICROCELLS: u64> Debug
             .field("rect", &self.rect)
             .field("cols", &self.cols)
             .field("rows", &self.rows)
-            .field("cell_width", &self.cell_width)
-            .field("cell_height", &self.cell_height)
+            .field("cell_width", &self.cell_width())
+            .field("cell_height", &self.cell_height())
+            .field("transform", &self.transform())
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use proptest::proptest;
+
+    struct View;
+    impl Unit for View {
+        type Scalar = f32;
+    }
+
+    fn grid(swap_y: bool) -> GridMath<View> {
+        GridMath::new(
+            Rect::new(point!(10.0, 20.0), size!(60.0, 100.0)),
+            6,
+            5,
+            swap_y,
+        )
+    }
+
+    #[test]
+    fn view_to_index_bounds() {
+        for swap_y in [false, true] {
+            let grid = grid(swap_y);
+            assert_eq!(grid.view_to_index(Vector2::new(9.9, 50.0)), None);
+            assert_eq!(grid.view_to_index(Vector2::new(70.0, 50.0)), None);
+            assert_eq!(grid.view_to_index(Vector2::new(30.0, 19.9)), None);
+            assert_eq!(grid.view_to_index(Vector2::new(30.0, 120.0)), None);
+            assert_eq!(grid.view_to_index(Vector2::new(f32::NAN, 50.0)), None);
+
+            let top_left = grid.view_to_index(Vector2::new(10.0, 20.0)).unwrap();
+            assert_eq!(grid.rect_at_index(top_left).origin, point!(10.0, 20.0));
+            assert_eq!(
+                grid.view_to_index_clamped(Vector2::new(-100.0, -100.0)),
+                top_left
+            );
+
+            let bottom_right = grid.view_to_index(Vector2::new(69.9, 119.9)).unwrap();
+            assert_eq!(grid.rect_at_index(bottom_right).origin, point!(60.0, 100.0));
+            assert_eq!(
+                grid.view_to_index_clamped(Vector2::new(500.0, 500.0)),
+                bottom_right
+            );
+        }
+        assert_eq!(
+            grid(false).view_to_index(Vector2::new(10.0, 20.0)),
+            Some(CellIndex(0))
+        );
+        assert_eq!(
+            grid(true).view_to_index(Vector2::new(10.0, 20.0)),
+            Some(CellIndex(24))
+        );
+    }
+
+    #[test]
+    fn distance_from_center_in_cells() {
+        let grid = grid(true);
+        let index = grid.view_to_index(Vector2::new(35.0, 50.0)).unwrap();
+        let center = grid.center_at_index(index);
+        assert_eq!(center, point!(35.0, 50.0));
+        // Half a cell to the right and to the bottom
+        let corner = Vector2::new(40.0, 60.0);
+        assert_relative_eq!(grid.distance_from_center(corner, index), 0.70710677);
+        assert!(grid.hits_cell(corner, index, 0.75));
+        assert!(!grid.hits_cell(corner, index, 1.0 / 1.8));
+        assert!(grid.hits_cell(Vector2::new(38.0, 50.0), index, 1.0 / 1.8));
+    }
+
+    #[test]
+    fn leaning_neighbour() {
+        for swap_y in [false, true] {
+            let grid = grid(swap_y);
+            let index = grid.view_to_index(Vector2::new(35.0, 50.0)).unwrap();
+            let right = grid.view_to_index(Vector2::new(45.0, 50.0)).unwrap();
+            let below = grid.view_to_index(Vector2::new(35.0, 70.0)).unwrap();
+            assert_eq!(grid.leaning_neighbour(Vector2::new(35.0, 50.0)), None);
+            assert_eq!(
+                grid.leaning_neighbour(Vector2::new(39.0, 52.0)),
+                Some(right)
+            );
+            assert_eq!(
+                grid.leaning_neighbour(Vector2::new(36.0, 58.0)),
+                Some(below)
+            );
+            assert_eq!(grid.grid_distance(index, right), 1);
+            // Border cells lean outside of the grid
+            assert_eq!(grid.leaning_neighbour(Vector2::new(11.0, 30.0)), None);
+        }
+    }
+
+    const METRICS: [GridMetric; 5] = [
+        GridMetric::Manhattan,
+        GridMetric::Chebyshev,
+        GridMetric::Euclidean,
+        GridMetric::OCTILE,
+        GridMetric::Diagonal { diagonal: 0.5 },
+    ];
+
+    #[test]
+    fn metric_distances() {
+        assert_eq!(GridMetric::Manhattan.distance(3, 4), 7.0);
+        assert_eq!(GridMetric::Chebyshev.distance(3, 4), 4.0);
+        assert_eq!(GridMetric::Euclidean.distance(3, 4), 5.0);
+        assert_relative_eq!(
+            GridMetric::OCTILE.distance(3, 4),
+            3.0 * core::f32::consts::SQRT_2 + 1.0
+        );
+        assert_eq!(GridMetric::Diagonal { diagonal: 2.0 }.distance(3, 4), 7.0);
+        assert_eq!(GridMetric::Diagonal { diagonal: 1.0 }.distance(3, 4), 4.0);
+
+        let grid = grid(true);
+        let a = grid.view_to_index(Vector2::new(15.0, 25.0)).unwrap();
+        let b = grid.view_to_index(Vector2::new(45.0, 105.0)).unwrap();
+        assert_eq!(grid.grid_distance(a, b), 4);
+        assert_eq!(grid.metric_distance(a, b, GridMetric::Manhattan), 7.0);
+        assert_eq!(grid.metric_distance(a, b, GridMetric::Euclidean), 5.0);
+    }
+
+    #[test]
+    fn range_queries() {
+        let grid = grid(false);
+        let center = CellIndex(grid.linearize(2, 2));
+        let count = |metric, radius| grid.cells_within(center, radius, metric).count();
+        assert_eq!(count(GridMetric::Manhattan, 1.0), 5);
+        assert_eq!(count(GridMetric::Chebyshev, 1.0), 9);
+        assert_eq!(count(GridMetric::Euclidean, 1.0), 5);
+        assert_eq!(count(GridMetric::Euclidean, 1.5), 9);
+        assert_eq!(count(GridMetric::OCTILE, 1.5), 9);
+        assert_eq!(count(GridMetric::Manhattan, 2.0), 13);
+        // Cells right at the radius are found despite rounding errors
+        assert_eq!(count(GridMetric::Manhattan, 2.0 - 1e-5), 13);
+        // Cheap diagonals reach further than the radius along each axis
+        assert_eq!(count(GridMetric::Diagonal { diagonal: 0.5 }, 1.0), 13);
+        assert_eq!(GridMetric::Diagonal { diagonal: 0.5 }.reach(1.0), 2);
+        assert_eq!(
+            GridMetric::Diagonal { diagonal: 0.0 }.reach(1.0),
+            usize::MAX
+        );
+        // Clipped by the grid border
+        assert_eq!(
+            grid.cells_within(CellIndex(0), 1.0, GridMetric::Chebyshev)
+                .count(),
+            4
+        );
+        for metric in METRICS {
+            assert_eq!(count(metric, 0.0), 1);
+            assert_eq!(count(metric, 100.0), 30);
+        }
+    }
+
+    #[test]
+    fn rings_cover_the_grid_once() {
+        let grid = grid(true);
+        let center = grid.view_to_index(Vector2::new(35.0, 50.0)).unwrap();
+        for metric in METRICS {
+            let mut seen = [0; 30];
+            for radius in 0..12 {
+                for cell in grid.ring(center, radius, metric) {
+                    seen[cell.0] += 1;
+                    let distance = grid.metric_distance(center, cell, metric);
+                    assert!(distance <= radius as f32 + 1e-4, "{metric:?}");
+                    assert!(distance > radius as f32 - 1.0, "{metric:?}");
+                }
+            }
+            assert_eq!(seen, [1; 30], "{metric:?}");
+        }
+        assert_eq!(
+            grid.ring(center, 0, GridMetric::Manhattan)
+                .collect::<Vec<_>>(),
+            vec![center]
+        );
+        assert_eq!(grid.ring(center, 1, GridMetric::Manhattan).count(), 4);
+        assert_eq!(grid.ring(center, 1, GridMetric::Chebyshev).count(), 8);
+        assert_eq!(grid.ring(center, 1, GridMetric::Euclidean).count(), 4);
+        // Diagonal neighbours and cells two steps away, one of which is cut
+        // off by the grid border
+        assert_eq!(grid.ring(center, 2, GridMetric::Euclidean).count(), 7);
+    }
+
+    /// View position at the given fractional cell coordinates
+    fn at(x: f32, y: f32) -> Vector2<View> {
+        Vector2::new(10.0 + x * 10.0, 20.0 + y * 20.0)
+    }
+
+    fn cell(grid: &GridMath<View>, x: usize, y: usize) -> CellIndex {
+        grid.view_to_index(at(x as f32 + 0.5, y as f32 + 0.5))
+            .unwrap()
+    }
+
+    #[test]
+    fn segment_rasterisation() {
+        let grid = grid(false);
+        let cells = |from, to| grid.segment_cells(from, to).collect::<Vec<_>>();
+
+        let row = cells(at(0.5, 1.5), at(3.5, 1.5));
+        assert_eq!(row, (0..4).map(|x| cell(&grid, x, 1)).collect::<Vec<_>>());
+
+        let back = cells(at(3.5, 1.5), at(0.5, 1.5));
+        assert_eq!(
+            back,
+            (0..4).rev().map(|x| cell(&grid, x, 1)).collect::<Vec<_>>()
+        );
+
+        // Exact diagonal passes through the horizontal neighbour first
+        let diagonal = cells(at(0.5, 0.5), at(2.5, 2.5));
+        assert_eq!(
+            diagonal,
+            [(0, 0), (1, 0), (1, 1), (2, 1), (2, 2)].map(|(x, y)| cell(&grid, x, y))
+        );
+        let diagonal = grid
+            .segment_cells(at(0.5, 0.5), at(2.5, 2.5))
+            .with_diagonals(true)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagonal,
+            [(0, 0), (1, 1), (2, 2)].map(|(x, y)| cell(&grid, x, y))
+        );
+
+        // Shallow line crosses a row border once
+        let shallow = cells(at(0.5, 0.2), at(4.5, 0.8 + 0.6));
+        assert_eq!(shallow.first(), Some(&cell(&grid, 0, 0)));
+        assert_eq!(shallow.last(), Some(&cell(&grid, 4, 1)));
+        assert_eq!(shallow.len(), 6);
+
+        // Parts outside of the grid are skipped
+        let outside = cells(at(-2.5, 0.5), at(1.5, 0.5));
+        assert_eq!(outside, [cell(&grid, 0, 0), cell(&grid, 1, 0)]);
+
+        assert_eq!(cells(at(1.2, 1.2), at(1.7, 1.3)), [cell(&grid, 1, 1)]);
+    }
+
+    #[test]
+    fn segment_hit_radius() {
+        let grid = grid(true);
+        // Passes near the corner, far from the centers of the cells it
+        // only clips
+        let cells = grid
+            .segment_cells(at(0.5, 0.5), at(2.5, 2.4))
+            .with_hit_radius(0.3)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            [(0, 0), (1, 1), (2, 2)].map(|(x, y)| cell(&grid, x, y))
+        );
+    }
+
+    #[test]
+    fn transformed_grid() {
+        // Zoomed twice around the origin, rotated by a quarter turn and moved
+        let transform = Affine2::from_translation(vec2(500.0, 0.0))
+            * Affine2::from_angle(core::f32::consts::FRAC_PI_2)
+            * Affine2::from_scale(vec2(2.0, 2.0));
+        let plain = grid(false);
+        let grid = grid(false).with_transform(transform);
+        assert_eq!(grid.transform(), Some(transform));
+
+        let index = cell(&plain, 2, 1);
+        // Board center (35, 50) is zoomed to (70, 100) and rotated to (-100, 70)
+        let center = grid.center_at_index(index);
+        assert_relative_eq!(center.x, 400.0, epsilon = 1e-3);
+        assert_relative_eq!(center.y, 70.0, epsilon = 1e-3);
+
+        let view_center = Vector2::new(center.x, center.y);
+        assert_eq!(grid.view_to_index(view_center), Some(index));
+        // Untransformed position of the cell now belongs to no cell
+        assert_eq!(grid.view_to_index(Vector2::new(35.0, 50.0)), None);
+
+        // Cell is 20x40 after zooming, and 40x20 after rotation
+        let rect = grid.rect_at_index(index);
+        assert_relative_eq!(rect.width(), 40.0, epsilon = 1e-3);
+        assert_relative_eq!(rect.height(), 20.0, epsilon = 1e-3);
+        assert_relative_eq!(rect.center().x, center.x, epsilon = 1e-3);
+
+        // Moving 15 view units along x moves 7.5 board units along -y, which
+        // is 0.375 of the cell height
+        let moved = Vector2::new(center.x + 15.0, center.y);
+        assert_relative_eq!(
+            grid.offset_from_center(moved, index).1,
+            -0.375,
+            epsilon = 1e-4
+        );
+        assert!(grid.hits_cell(moved, index, 0.4));
+        assert!(!grid.hits_cell(moved, index, 0.35));
+
+        // Board row becomes a view column
+        let above = plain.center_at_index(cell(&plain, 2, 3));
+        let above =
+            grid.center_at_index(plain.view_to_index(Vector2::new(above.x, above.y)).unwrap());
+        let cells = grid
+            .segment_cells(view_center, Vector2::new(above.x, above.y))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            [(2, 1), (2, 2), (2, 3)].map(|(x, y)| cell(&plain, x, y))
+        );
+    }
+
+    #[test]
+    fn singular_transform_is_ignored() {
+        let zoom = Affine2::from_scale(vec2(2.0, 2.0));
+        let mut grid = grid(false).with_transform(zoom);
+        let index = cell(&grid, 2, 1);
+        let center = grid.center_at_index(index);
+        let center = Vector2::new(center.x, center.y);
+
+        assert!(!grid.set_transform(Some(Affine2::from_scale(vec2(0.0, 2.0)))));
+        assert_eq!(grid.transform(), Some(zoom));
+        assert_eq!(grid.view_to_index_clamped(center), index);
+
+        let grid = grid.with_transform(Affine2::ZERO);
+        assert_eq!(grid.transform(), Some(zoom));
+        assert_eq!(grid.view_to_index(center), Some(index));
+    }
+
+    proptest! {
+        #[test]
+        fn center_round_trip(index in 0usize..30, swap_y: bool) {
+            let grid = grid(swap_y);
+            let center = grid.center_at_index(CellIndex(index));
+            let pos = Vector2::new(center.x, center.y);
+            assert_eq!(grid.view_to_index(pos), Some(CellIndex(index)));
+            assert_eq!(grid.view_to_index_clamped(pos), CellIndex(index));
+            assert_relative_eq!(grid.distance_from_center(pos, CellIndex(index)), 0.0);
+        }
+
+        #[test]
+        fn clamped_matches_checked(x in -50f32..150f32, y in -50f32..200f32, swap_y: bool) {
+            let grid = grid(swap_y);
+            let pos = Vector2::new(x, y);
+            if let Some(index) = grid.view_to_index(pos) {
+                assert_eq!(grid.view_to_index_clamped(pos), index);
+                assert!(grid.distance_from_center(pos, index) <= 0.7072);
+            }
+        }
+
+        #[cfg(feature = "fixed")]
+        #[test]
+        fn fixed_geometry_matches_float(x in -50f32..150f32, y in -50f32..200f32, swap_y: bool) {
+            use crate::fixed::Fixed;
+
+            let grid = grid(swap_y);
+            let geometry = grid.geometry::<Fixed>();
+            // Keep clear of cell borders, where rounding may differ
+            let [cx, cy] = grid.geometry::<f32>().cell_coords([x, y]);
+            let margin = 1e-3;
+            if (cx - libm::roundf(cx)).abs() > margin && (cy - libm::roundf(cy)).abs() > margin {
+                let pos = [Fixed::from_f32(x), Fixed::from_f32(y)];
+                assert_eq!(geometry.view_to_index(pos), grid.view_to_index(Vector2::new(x, y)));
+                assert_eq!(
+                    geometry.view_to_index_clamped(pos),
+                    grid.view_to_index_clamped(Vector2::new(x, y))
+                );
+            }
+        }
+
+        #[test]
+        fn segment_cells_are_connected(
+            x1 in -1f32..7.0,
+            y1 in -1f32..6.0,
+            x2 in -1f32..7.0,
+            y2 in -1f32..6.0,
+            diagonals: bool,
+            swap_y: bool,
+        ) {
+            let grid = grid(swap_y);
+            let (from, to) = (at(x1, y1), at(x2, y2));
+            let cells = grid
+                .segment_cells(from, to)
+                .with_diagonals(diagonals)
+                .collect::<Vec<_>>();
+            if let Some(start) = grid.view_to_index(from) {
+                assert_eq!(cells.first(), Some(&start));
+            }
+            if let Some(end) = grid.view_to_index(to) {
+                assert_eq!(cells.last(), Some(&end));
+            }
+            let all_inside = [from, to].iter().all(|&p| grid.view_to_index(p).is_some());
+            for pair in cells.windows(2) {
+                assert_ne!(pair[0], pair[1]);
+                if all_inside {
+                    let (dx, dy) = grid.cell_delta(pair[0], pair[1]);
+                    if diagonals {
+                        assert!(dx.max(dy) == 1);
+                    } else {
+                        assert_eq!(dx + dy, 1);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use crate::scalar::Real;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Signed fixed-point number with 32 integer and 32 fractional bits
+///
+/// All operations, including square roots and trigonometry, use integer
+/// arithmetic only, so results match bit-for-bit on every platform.
+/// Arithmetic overflow panics in debug builds, like with integers
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Fixed(i64);
+
+const FRACTION_BITS: u32 = 32;
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << FRACTION_BITS);
+    pub const HALF: Fixed = Fixed(1 << (FRACTION_BITS - 1));
+    pub const PI: Fixed = Fixed(13493037705);
+    pub const FRAC_PI_2: Fixed = Fixed(6746518852);
+    pub const MAX: Fixed = Fixed(i64::MAX);
+    pub const MIN: Fixed = Fixed(i64::MIN);
+    /// Smallest positive value
+    pub const EPSILON: Fixed = Fixed(1);
+
+    pub const fn from_bits(bits: i64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> i64 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Self {
+        Self((value as i64) << FRACTION_BITS)
+    }
+
+    /// Converts a float, rounding to the closest representable value
+    ///
+    /// Values out of range are saturated, and NaN is converted to zero
+    pub fn from_f32(value: f32) -> Self {
+        Self(libm::round(value as f64 * (1u64 << FRACTION_BITS) as f64) as i64)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u64 << FRACTION_BITS) as f64
+    }
+
+    pub fn floor(self) -> Self {
+        Self(self.0 & !(Self::ONE.0 - 1))
+    }
+
+    /// Multiplication, returning `None` on overflow
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        i64::try_from(wide_mul(self, rhs)).ok().map(Self)
+    }
+
+    /// Division, returning `None` on overflow or division by zero
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        i64::try_from(wide_div(self, rhs)).ok().map(Self)
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// Square root, returning zero for negative values
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        Self(((self.0 as u128) << FRACTION_BITS).isqrt() as i64)
+    }
+
+    pub fn sin(self) -> Self {
+        // Reduce to -PI..=PI
+        let tau = Self::PI.0 * 2;
+        let mut x = Self(self.0.rem_euclid(tau));
+        if x > Self::PI {
+            x = Self(x.0 - tau);
+        }
+        // Reduce to -PI/2..=PI/2, where the series converges quickly
+        if x > Self::FRAC_PI_2 {
+            x = Self::PI - x;
+        } else if x < -Self::FRAC_PI_2 {
+            x = -Self::PI - x;
+        }
+        let x2 = x * x;
+        // Taylor series up to x^15, evaluated from the highest term
+        let mut term = Self::ONE;
+        for n in (1..=7).rev() {
+            let divisor = (2 * n) * (2 * n + 1);
+            term = Self::ONE - x2 * term / Self::from_int(divisor);
+        }
+        x * term
+    }
+
+    pub fn cos(self) -> Self {
+        (self + Self::FRAC_PI_2).sin()
+    }
+
+    pub fn atan(self) -> Self {
+        if self.0 < 0 {
+            return -(-self).atan();
+        }
+        if self > Self::ONE {
+            return Self::FRAC_PI_2 - (Self::ONE / self).atan();
+        }
+        // Halve the angle twice with atan(x) = 2 * atan(x / (1 + sqrt(1 + x^2))),
+        // leaving |x| <= tan(PI / 16)
+        let mut x = self;
+        for _ in 0..2 {
+            x = x / (Self::ONE + (Self::ONE + x * x).sqrt());
+        }
+        let x2 = x * x;
+        // Alternating series up to x^15
+        let mut sum = Self::ZERO;
+        for n in (0..8).rev() {
+            sum = Self::ONE / Self::from_int(2 * n + 1) - x2 * sum;
+        }
+        x * sum * Self::from_int(4)
+    }
+
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        if y.0.unsigned_abs() > x.0.unsigned_abs() {
+            // Divide by the larger side, as `y / x` overflows for tiny `x`
+            let quarter = if y.0 > 0 {
+                Self::FRAC_PI_2
+            } else {
+                -Self::FRAC_PI_2
+            };
+            return quarter - (x / y).atan();
+        }
+        if x.0 > 0 {
+            (y / x).atan()
+        } else if x.0 < 0 {
+            if y.0 >= 0 {
+                (y / x).atan() + Self::PI
+            } else {
+                (y / x).atan() - Self::PI
+            }
+        } else {
+            Self::ZERO
+        }
+    }
+
+    pub fn asin(self) -> Self {
+        self.atan2((Self::ONE - self * self).sqrt())
+    }
+
+    pub fn acos(self) -> Self {
+        (Self::ONE - self * self).sqrt().atan2(self)
+    }
+}
+
+impl Debug for Fixed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Fixed({})", self.to_f64())
+    }
+}
+
+impl Display for Fixed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.to_f64(), f)
+    }
+}
+
+impl From<i32> for Fixed {
+    fn from(value: i32) -> Self {
+        Self::from_int(value)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+fn wide_mul(a: Fixed, b: Fixed) -> i128 {
+    (a.0 as i128 * b.0 as i128) >> FRACTION_BITS
+}
+
+fn wide_div(a: Fixed, b: Fixed) -> i128 {
+    ((a.0 as i128) << FRACTION_BITS) / b.0 as i128
+}
+
+/// Brings the result of a wide operation back to the fixed-point range,
+/// panicking on overflow in debug builds and wrapping around otherwise
+fn narrow(value: i128) -> Fixed {
+    if cfg!(debug_assertions) {
+        Fixed(i64::try_from(value).expect("Fixed overflow"))
+    } else {
+        Fixed(value as i64)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        narrow(wide_mul(self, rhs))
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    /// # Panics
+    /// Panics on division by zero
+    fn div(self, rhs: Self) -> Self::Output {
+        narrow(wide_div(self, rhs))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Fixed {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Real for Fixed {
+    const ZERO: Self = Fixed::ZERO;
+    const ONE: Self = Fixed::ONE;
+    const PI: Self = Fixed::PI;
+    const FRAC_PI_2: Self = Fixed::FRAC_PI_2;
+
+    fn from_f32(value: f32) -> Self {
+        Fixed::from_f32(value)
+    }
+
+    fn to_f32(self) -> f32 {
+        Fixed::to_f32(self)
+    }
+
+    fn from_int(value: i32) -> Self {
+        Fixed::from_int(value)
+    }
+
+    fn floor_to_int(self) -> i64 {
+        self.0 >> FRACTION_BITS
+    }
+
+    fn floor(self) -> Self {
+        Fixed::floor(self)
+    }
+
+    fn abs(self) -> Self {
+        Fixed::abs(self)
+    }
+
+    fn sqrt(self) -> Self {
+        Fixed::sqrt(self)
+    }
+
+    fn sin(self) -> Self {
+        Fixed::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        Fixed::cos(self)
+    }
+
+    fn asin(self) -> Self {
+        Fixed::asin(self)
+    }
+
+    fn acos(self) -> Self {
+        Fixed::acos(self)
+    }
+
+    fn atan(self) -> Self {
+        Fixed::atan(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        Fixed::atan2(self, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use proptest::proptest;
+
+    fn fixed(value: f64) -> Fixed {
+        Fixed::from_f32(value as f32)
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(Fixed::from_int(3) * Fixed::HALF, fixed(1.5));
+        assert_eq!(Fixed::from_int(3) / Fixed::from_int(4), fixed(0.75));
+        assert_eq!(fixed(-1.25).floor(), Fixed::from_int(-2));
+        assert_eq!(fixed(1.75).floor(), Fixed::ONE);
+        assert_eq!(Fixed::from_int(9).sqrt(), Fixed::from_int(3));
+        assert_eq!(Fixed::from_int(-9).sqrt(), Fixed::ZERO);
+        assert_eq!(Real::floor_to_int(fixed(-0.5)), -1);
+        assert_relative_eq!(Fixed::PI.to_f64(), core::f64::consts::PI, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let big = Fixed::from_int(70000);
+        assert_eq!(big.checked_mul(big), None);
+        assert_eq!(big.checked_mul(-big), None);
+        assert_eq!(
+            Fixed::from_int(3).checked_mul(Fixed::HALF),
+            Some(fixed(1.5))
+        );
+        assert_eq!(big.checked_div(Fixed::EPSILON), None);
+        assert_eq!(Fixed::ONE.checked_div(Fixed::ZERO), None);
+        assert_eq!(
+            Fixed::from_int(3).checked_div(Fixed::from_int(4)),
+            Some(fixed(0.75))
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Fixed overflow")]
+    fn overflow_panics_in_debug() {
+        let big = Fixed::from_int(70000);
+        let _ = big * big;
+    }
+
+    #[test]
+    fn trigonometry() {
+        assert_eq!(Fixed::ZERO.sin(), Fixed::ZERO);
+        assert_relative_eq!(Fixed::FRAC_PI_2.sin().to_f64(), 1.0, epsilon = 1e-8);
+        assert_relative_eq!(Fixed::PI.cos().to_f64(), -1.0, epsilon = 1e-8);
+        assert_relative_eq!(
+            Fixed::ONE.atan().to_f64(),
+            core::f64::consts::FRAC_PI_4,
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            Fixed::from_int(-1).atan2(Fixed::from_int(-1)).to_f64(),
+            -3.0 * core::f64::consts::FRAC_PI_4,
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            Fixed::ZERO.acos().to_f64(),
+            core::f64::consts::FRAC_PI_2,
+            epsilon = 1e-8
+        );
+    }
+
+    #[test]
+    fn atan2_with_tiny_x() {
+        assert_relative_eq!(
+            Fixed::ONE.atan2(Fixed::from_bits(1)).to_f64(),
+            core::f64::consts::FRAC_PI_2,
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            (-Fixed::ONE).atan2(Fixed::from_bits(-1)).to_f64(),
+            -core::f64::consts::FRAC_PI_2,
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            Fixed::from_bits(1).acos().to_f64(),
+            core::f64::consts::FRAC_PI_2,
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            Fixed::from_int(100000)
+                .atan2(Fixed::from_f32(1e-5))
+                .to_f64(),
+            core::f64::consts::FRAC_PI_2,
+            epsilon = 1e-8
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn matches_float_math(a in -100f64..100.0, b in 0.01f64..100.0) {
+            let (fa, fb) = (fixed(a), fixed(b));
+            let (a, b) = (fa.to_f64(), fb.to_f64());
+            assert_relative_eq!((fa * fb).to_f64(), a * b, epsilon = 1e-6);
+            assert_relative_eq!((fa / fb).to_f64(), a / b, epsilon = 1e-6);
+            assert_relative_eq!(fb.sqrt().to_f64(), libm::sqrt(b), epsilon = 1e-8);
+            assert_relative_eq!(fa.sin().to_f64(), libm::sin(a), epsilon = 1e-7);
+            assert_relative_eq!(fa.cos().to_f64(), libm::cos(a), epsilon = 1e-7);
+            assert_relative_eq!(fa.atan().to_f64(), libm::atan(a), epsilon = 1e-8);
+            assert_relative_eq!(fa.atan2(fb).to_f64(), libm::atan2(a, b), epsilon = 1e-8);
+            assert_relative_eq!(fb.atan2(fa).to_f64(), libm::atan2(b, a), epsilon = 1e-8);
+        }
+
+        #[test]
+        fn inverse_trigonometry(x in -1f64..=1.0) {
+            let fx = fixed(x);
+            let x = fx.to_f64();
+            assert_relative_eq!(fx.asin().to_f64(), libm::asin(x), epsilon = 1e-4);
+            assert_relative_eq!(fx.acos().to_f64(), libm::acos(x), epsilon = 1e-4);
+        }
+    }
+}
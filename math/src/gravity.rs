@@ -1,25 +1,243 @@
-use crate::lib_ext::FloatLibmExt;
+use crate::scalar::Real;
+use glam::{vec2, Vec2};
 
 /// Calculate the time it takes to fall from a height with gravity
-pub fn fall_time(height: f32, g: f32) -> f32 {
-    (2.0 * height / g).sqrt()
+pub fn fall_time<S: Real>(height: S, g: S) -> S {
+    (S::from_int(2) * height / g).sqrt()
 }
 
 /// Calculate the height at certain progress of falling
-pub fn height_at_fall_progress(initial_height: f32, g: f32, progress: f32) -> f32 {
+pub fn height_at_fall_progress<S: Real>(initial_height: S, g: S, progress: S) -> S {
     let time = fall_time(initial_height, g);
-    initial_height - g * (progress * time).powi_m(2) / 2.0
+    initial_height - g * (progress * time).powi(2) / S::from_int(2)
 }
 
 /// Calculate the gravity from the height and the time it takes to fall
-pub fn gravity_from_fall_time(height: f32, time: f32) -> f32 {
-    height * 2.0 / time
+///
+/// Inverse of [fall_time]
+pub fn gravity_from_fall_time<S: Real>(height: S, time: S) -> S {
+    S::from_int(2) * height / time.powi(2)
+}
+
+/// Falling motion towards a target, with optional initial velocity, terminal
+/// velocity and bounces off the target
+///
+/// Distances and velocities are measured downwards, so the height is the
+/// distance left to the target, and negative initial velocity throws the
+/// object upwards first
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FallMotion<S: Real = f32> {
+    pub height: S,
+    pub gravity: S,
+    pub initial_velocity: S,
+    /// Maximal falling speed, [None] for no limit
+    pub terminal_velocity: Option<S>,
+    /// Part of the impact speed kept after bouncing off the target
+    pub restitution: S,
+    pub max_bounces: u32,
+    /// Stretch of the object at the impact speed, see [FallState::stretch]
+    pub stretch: S,
+    /// Time it takes to recover from the squash after landing
+    pub squash_time: S,
+}
+
+/// State of the falling object at a certain time
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FallState<S: Real = f32> {
+    /// Distance left to the target, or height of the bounce
+    pub height: S,
+    /// Downwards velocity
+    pub velocity: S,
+    /// Vertical squash/stretch factor, above 1 when stretched by the speed
+    /// and below 1 when squashed by landing
+    pub stretch: S,
+    /// Number of times the object has hit the target so far
+    pub landings: u32,
+}
+
+impl FallState<f32> {
+    /// Scale of the object for the stretch factor, keeping its area
+    pub fn scale(&self) -> Vec2 {
+        vec2(1.0 / self.stretch, self.stretch)
+    }
+}
+
+impl<S: Real> FallMotion<S> {
+    /// Plain free fall from rest
+    pub fn new(height: S, gravity: S) -> Self {
+        Self {
+            height,
+            gravity,
+            initial_velocity: S::ZERO,
+            terminal_velocity: None,
+            restitution: S::ZERO,
+            max_bounces: 0,
+            stretch: S::ZERO,
+            squash_time: S::ZERO,
+        }
+    }
+
+    /// Starts the fall with the given velocity, for example to continue a
+    /// previous fall
+    pub fn with_initial_velocity(mut self, velocity: S) -> Self {
+        self.initial_velocity = velocity;
+        self
+    }
+
+    pub fn with_terminal_velocity(mut self, velocity: S) -> Self {
+        self.terminal_velocity = Some(velocity);
+        self
+    }
+
+    /// Makes the object bounce off the target up to `max_bounces` times
+    pub fn with_bounces(mut self, restitution: S, max_bounces: u32) -> Self {
+        self.restitution = restitution.clamp(S::ZERO, S::ONE);
+        self.max_bounces = max_bounces;
+        self
+    }
+
+    /// Stretches the object by `stretch` at the impact speed, and squashes
+    /// it by the same amount on landing, recovering in `squash_time`
+    pub fn with_squash_stretch(mut self, stretch: S, squash_time: S) -> Self {
+        self.stretch = stretch;
+        self.squash_time = squash_time;
+        self
+    }
+
+    /// Initial velocity, limited by the terminal velocity
+    fn start_velocity(&self) -> S {
+        match self.terminal_velocity {
+            Some(terminal) => self.initial_velocity.min(terminal),
+            None => self.initial_velocity,
+        }
+    }
+
+    /// Time and distance it takes to reach the terminal velocity, along with
+    /// the velocity itself
+    fn terminal_point(&self) -> Option<(S, S, S)> {
+        let v0 = self.start_velocity();
+        let vt = self.terminal_velocity?;
+        Some((
+            (vt - v0) / self.gravity,
+            (vt * vt - v0 * v0) / (S::from_int(2) * self.gravity),
+            vt,
+        ))
+    }
+
+    /// Time it takes to reach the target for the first time
+    pub fn fall_time(&self) -> S {
+        let v0 = self.start_velocity();
+        match self.terminal_point() {
+            Some((t1, d1, vt)) if d1 < self.height => t1 + (self.height - d1) / vt,
+            _ => {
+                ((v0 * v0 + S::from_int(2) * self.gravity * self.height).sqrt() - v0) / self.gravity
+            }
+        }
+    }
+
+    /// Speed at which the object hits the target for the first time
+    pub fn impact_velocity(&self) -> S {
+        let v0 = self.start_velocity();
+        let velocity = (v0 * v0 + S::from_int(2) * self.gravity * self.height).sqrt();
+        match self.terminal_velocity {
+            Some(terminal) => velocity.min(terminal),
+            None => velocity,
+        }
+    }
+
+    /// Speed of the object right after the given bounce
+    fn bounce_velocity(&self, bounce: u32) -> S {
+        self.impact_velocity() * self.restitution.powi(bounce)
+    }
+
+    fn bounce_time(&self, bounce: u32) -> S {
+        S::from_int(2) * self.bounce_velocity(bounce) / self.gravity
+    }
+
+    /// Time it takes to come to rest on the target, including bounces
+    pub fn duration(&self) -> S {
+        (1..=self.max_bounces).fold(self.fall_time(), |time, bounce| {
+            time + self.bounce_time(bounce)
+        })
+    }
+
+    /// Distance fallen and velocity at the given time of the first fall
+    fn fall_at(&self, time: S) -> (S, S) {
+        let v0 = self.start_velocity();
+        match self.terminal_point() {
+            Some((t1, d1, vt)) if time >= t1 => (d1 + vt * (time - t1), vt),
+            _ => (
+                v0 * time + self.gravity * time * time / S::from_int(2),
+                v0 + self.gravity * time,
+            ),
+        }
+    }
+
+    /// Calculates the state of the object at the given time since the start
+    pub fn state_at(&self, time: S) -> FallState<S> {
+        let time = time.max(S::ZERO);
+        let fall_time = self.fall_time();
+        let impact = self.impact_velocity();
+        if time < fall_time {
+            let (fallen, velocity) = self.fall_at(time);
+            return FallState {
+                height: self.height - fallen,
+                velocity,
+                stretch: self.stretch_factor(velocity, impact),
+                landings: 0,
+            };
+        }
+
+        // Find the bounce the object is in, or the rest after the last one
+        let mut since_landing = time - fall_time;
+        let mut landings = 1;
+        while landings <= self.max_bounces {
+            let bounce_time = self.bounce_time(landings);
+            if since_landing < bounce_time {
+                break;
+            }
+            since_landing = since_landing - bounce_time;
+            landings += 1;
+        }
+
+        let landing_velocity = self.bounce_velocity(landings - 1);
+        let squash = if self.squash_time > S::ZERO && since_landing < self.squash_time {
+            self.stretch * landing_velocity / impact * (S::ONE - since_landing / self.squash_time)
+        } else {
+            S::ZERO
+        };
+
+        let (height, velocity) = if landings <= self.max_bounces {
+            let up = self.bounce_velocity(landings);
+            (
+                up * since_landing - self.gravity * since_landing * since_landing / S::from_int(2),
+                self.gravity * since_landing - up,
+            )
+        } else {
+            (S::ZERO, S::ZERO)
+        };
+
+        FallState {
+            height,
+            velocity,
+            stretch: self.stretch_factor(velocity, impact) - squash,
+            landings: landings.min(self.max_bounces + 1),
+        }
+    }
+
+    fn stretch_factor(&self, velocity: S, impact: S) -> S {
+        if impact <= S::ZERO {
+            return S::ONE;
+        }
+        S::ONE + self.stretch * velocity.abs() / impact
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
     use approx::assert_relative_eq;
+    use proptest::proptest;
 
     #[test]
     pub fn should_calculate_fall_time() {
@@ -37,4 +255,146 @@ pub mod tests {
         assert_relative_eq!(height_at_fall_progress(1.0, 18.0, 0.75), 0.4375);
         assert_relative_eq!(height_at_fall_progress(1.0, 18.0, 1.0), 0.0);
     }
+
+    #[test]
+    pub fn should_calculate_gravity_from_fall_time() {
+        assert_relative_eq!(gravity_from_fall_time(1.0, 1.0 / 3.0), 18.0, epsilon = 1e-4);
+        assert_relative_eq!(gravity_from_fall_time(4.0, 2.0 / 3.0), 18.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    pub fn should_limit_fall_by_terminal_velocity() {
+        let fall = FallMotion::new(10.0, 2.0).with_terminal_velocity(4.0);
+        // 2 seconds to reach 4 units/s over 4 units, then 6 units at 4 units/s
+        assert_relative_eq!(fall.fall_time(), 3.5);
+        assert_relative_eq!(fall.impact_velocity(), 4.0);
+        assert_relative_eq!(fall.state_at(1.0).height, 9.0);
+        assert_relative_eq!(fall.state_at(3.0).height, 2.0);
+        assert_relative_eq!(fall.state_at(3.0).velocity, 4.0);
+    }
+
+    #[test]
+    pub fn should_bounce() {
+        let fall = FallMotion::new(2.0, 1.0).with_bounces(0.5, 2);
+        // Hits the target at 2 units/s, bounces at 1 and 0.5 units/s
+        assert_relative_eq!(fall.fall_time(), 2.0);
+        assert_relative_eq!(fall.duration(), 2.0 + 2.0 + 1.0);
+
+        let top = fall.state_at(3.0);
+        assert_relative_eq!(top.height, 0.5);
+        assert_relative_eq!(top.velocity, 0.0);
+        assert_eq!(top.landings, 1);
+
+        assert_eq!(fall.state_at(4.5).landings, 2);
+        let rest = fall.state_at(10.0);
+        assert_eq!(rest.height, 0.0);
+        assert_eq!(rest.velocity, 0.0);
+        assert_eq!(rest.landings, 3);
+    }
+
+    #[test]
+    pub fn should_squash_and_stretch() {
+        let fall = FallMotion::new(2.0, 1.0).with_squash_stretch(0.2, 0.5);
+        assert_relative_eq!(fall.state_at(0.0).stretch, 1.0);
+        assert_relative_eq!(fall.state_at(1.0).stretch, 1.1);
+        assert_relative_eq!(fall.state_at(2.0).stretch, 0.8);
+        assert_relative_eq!(fall.state_at(2.25).stretch, 0.9);
+        assert_relative_eq!(fall.state_at(3.0).stretch, 1.0);
+
+        let scale = fall.state_at(1.0).scale();
+        assert_relative_eq!(scale.x * scale.y, 1.0);
+    }
+
+    #[cfg(feature = "fixed")]
+    #[test]
+    pub fn should_match_fixed_point() {
+        use crate::fixed::Fixed;
+
+        assert_eq!(
+            fall_time(Fixed::from_int(1), Fixed::from_int(32)),
+            Fixed::from_f32(0.25)
+        );
+        let fall = FallMotion::new(Fixed::from_int(10), Fixed::from_int(2))
+            .with_terminal_velocity(Fixed::from_int(4))
+            .with_bounces(Fixed::HALF, 2);
+        let float = FallMotion::new(10.0, 2.0)
+            .with_terminal_velocity(4.0)
+            .with_bounces(0.5, 2);
+        assert_relative_eq!(fall.duration().to_f32(), float.duration(), epsilon = 1e-5);
+        for time in 0..12 {
+            let state = fall.state_at(Fixed::from_int(time));
+            let expected = float.state_at(time as f32);
+            assert_relative_eq!(state.height.to_f32(), expected.height, epsilon = 1e-4);
+            assert_relative_eq!(state.velocity.to_f32(), expected.velocity, epsilon = 1e-4);
+            assert_eq!(state.landings, expected.landings);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn gravity_inverts_fall_time(height in 0.1f32..100.0, g in 0.1f32..100.0) {
+            let time = fall_time(height, g);
+            assert_relative_eq!(gravity_from_fall_time(height, time), g, max_relative = 1e-4);
+        }
+
+        #[test]
+        fn motion_matches_free_fall(height in 0.1f32..100.0, g in 0.1f32..100.0, progress in 0f32..=1.0) {
+            let fall = FallMotion::new(height, g);
+            assert_relative_eq!(fall.fall_time(), fall_time(height, g), max_relative = 1e-4);
+            let state = fall.state_at(progress * fall.fall_time());
+            assert_relative_eq!(
+                state.height,
+                height_at_fall_progress(height, g, progress),
+                epsilon = 1e-3 * height
+            );
+        }
+
+        #[test]
+        fn chained_falls_match_single_fall(
+            height in 0.1f32..100.0,
+            g in 0.1f32..100.0,
+            split in 0.05f32..0.95,
+            terminal in 1f32..100.0,
+        ) {
+            let whole = FallMotion::new(height, g).with_terminal_velocity(terminal);
+            let first = FallMotion::new(height * split, g).with_terminal_velocity(terminal);
+            let second = FallMotion::new(height * (1.0 - split), g)
+                .with_terminal_velocity(terminal)
+                .with_initial_velocity(first.impact_velocity());
+            assert_relative_eq!(
+                first.fall_time() + second.fall_time(),
+                whole.fall_time(),
+                max_relative = 1e-3
+            );
+            assert_relative_eq!(second.impact_velocity(), whole.impact_velocity(), max_relative = 1e-3);
+        }
+
+        #[test]
+        fn motion_stays_within_limits(
+            height in 0.1f32..100.0,
+            g in 0.1f32..100.0,
+            terminal in 1f32..100.0,
+            restitution in 0f32..0.9,
+            bounces in 0u32..5,
+            time in 0f32..100.0,
+        ) {
+            let fall = FallMotion::new(height, g)
+                .with_terminal_velocity(terminal)
+                .with_bounces(restitution, bounces);
+            let state = fall.state_at(time);
+            assert!(state.velocity <= terminal * (1.0 + 1e-4));
+            assert!(state.height >= -1e-3 * height);
+            assert!(state.height <= height * (1.0 + 1e-4));
+            if time >= fall.duration() {
+                assert_eq!(state.height, 0.0);
+                assert_eq!(state.landings, bounces + 1);
+            }
+            // Landing at the end of the fall
+            assert_relative_eq!(
+                fall.state_at(fall.fall_time() * (1.0 - 1e-6)).height,
+                0.0,
+                epsilon = 1e-3 * height.max(1.0)
+            );
+        }
+    }
 }
@@ -0,0 +1,455 @@
+use crate::board::CellIndex;
+use core::fmt::{Debug, Formatter};
+use core::ops::{Add, Mul, Sub};
+use glamour::{point, size, Box2, Point2, Rect, Unit, Vector2};
+use ndshape::{RuntimeShape, Shape};
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Orientation of hexes in a grid
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum HexOrientation {
+    /// Hexes have a vertex at the top, rows are shifted by half a hex
+    PointyTop,
+    /// Hexes have an edge at the top, columns are shifted by half a hex
+    FlatTop,
+}
+
+/// Axial hex coordinates, with the third cube coordinate being `-q - r`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct HexCoord {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl HexCoord {
+    /// Neighbour offsets, going counter-clockwise starting from the right
+    pub const DIRECTIONS: [HexCoord; 6] = [
+        HexCoord::new(1, 0),
+        HexCoord::new(1, -1),
+        HexCoord::new(0, -1),
+        HexCoord::new(-1, 0),
+        HexCoord::new(-1, 1),
+        HexCoord::new(0, 1),
+    ];
+
+    pub const fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    /// Third cube coordinate
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    /// Rounds fractional axial coordinates to the hex containing them
+    pub fn round(q: f32, r: f32) -> Self {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // Cube coordinates always sum to zero, so the coordinate with the
+        // largest rounding error gets restored from the other two
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Self::new(rq as i32, rr as i32)
+    }
+
+    /// Number of steps between two hexes
+    pub fn distance(self, other: HexCoord) -> u32 {
+        let d = self - other;
+        (d.q.unsigned_abs() + d.r.unsigned_abs() + d.s().unsigned_abs()) / 2
+    }
+
+    pub fn neighbours(self) -> impl Iterator<Item = HexCoord> {
+        HexCoord::DIRECTIONS.into_iter().map(move |d| self + d)
+    }
+
+    /// Returns hexes exactly `radius` steps away, going counter-clockwise
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = HexCoord> {
+        let radius = radius as i32;
+        let start = self + HexCoord::DIRECTIONS[4] * radius;
+        let sides = if radius == 0 { 1 } else { 6 };
+        (0..sides).flat_map(move |side| {
+            let corner = HexCoord::DIRECTIONS[..side]
+                .iter()
+                .fold(start, |pos, &d| pos + d * radius);
+            (0..radius.max(1)).map(move |step| corner + HexCoord::DIRECTIONS[side] * step)
+        })
+    }
+
+    /// Returns hexes up to `radius` steps away, ring by ring starting from
+    /// this hex
+    pub fn spiral(self, radius: u32) -> impl Iterator<Item = HexCoord> {
+        (0..=radius).flat_map(move |ring| self.ring(ring))
+    }
+}
+
+impl Add for HexCoord {
+    type Output = HexCoord;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        HexCoord::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for HexCoord {
+    type Output = HexCoord;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        HexCoord::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl Mul<i32> for HexCoord {
+    type Output = HexCoord;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        HexCoord::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+/// Hexagonal counterpart of [crate::board::GridMath]
+///
+/// Cells are stored in offset coordinates, with odd rows shifted right for
+/// pointy-top hexes, and odd columns shifted down for flat-top ones. Hexes
+/// are stretched to fill the whole rect
+#[derive(Clone)]
+pub struct HexGridMath<ViewUnits: Unit<Scalar = f32>> {
+    rect: Rect<ViewUnits>,
+    shape: RuntimeShape<usize, 2>,
+    cols: usize,
+    rows: usize,
+    orientation: HexOrientation,
+    /// Horizontal distance from the hex center to its corner
+    hex_width: f32,
+    /// Vertical distance from the hex center to its corner
+    hex_height: f32,
+    swap_y: bool,
+}
+
+impl<ViewUnits: Unit<Scalar = f32>> HexGridMath<ViewUnits> {
+    pub fn new(
+        rect: Rect<ViewUnits>,
+        cols: usize,
+        rows: usize,
+        orientation: HexOrientation,
+        swap_y: bool,
+    ) -> Self {
+        let (hex_width, hex_height) = match orientation {
+            HexOrientation::PointyTop => (
+                rect.width() / (SQRT_3 * (cols as f32 + 0.5)),
+                rect.height() / (1.5 * rows as f32 + 0.5),
+            ),
+            HexOrientation::FlatTop => (
+                rect.width() / (1.5 * cols as f32 + 0.5),
+                rect.height() / (SQRT_3 * (rows as f32 + 0.5)),
+            ),
+        };
+        Self {
+            rect,
+            shape: RuntimeShape::<usize, 2>::new([cols, rows]),
+            cols,
+            rows,
+            orientation,
+            hex_width,
+            hex_height,
+            swap_y,
+        }
+    }
+
+    fn linearize(&self, x: usize, y: usize) -> usize {
+        if self.swap_y {
+            self.shape.linearize([x, self.rows - y - 1])
+        } else {
+            self.shape.linearize([x, y])
+        }
+    }
+
+    fn delinearize(&self, pos: usize) -> (usize, usize) {
+        let [x, mut y] = self.shape.delinearize(pos);
+        if self.swap_y {
+            y = self.rows - y - 1;
+        }
+
+        (x, y)
+    }
+
+    pub fn orientation(&self) -> HexOrientation {
+        self.orientation
+    }
+
+    /// Returns axial coordinates of a cell
+    pub fn index_to_hex(&self, index: CellIndex) -> HexCoord {
+        let (col, row) = self.delinearize(index.0);
+        let (col, row) = (col as i32, row as i32);
+        match self.orientation {
+            HexOrientation::PointyTop => HexCoord::new(col - (row - (row & 1)) / 2, row),
+            HexOrientation::FlatTop => HexCoord::new(col, row - (col - (col & 1)) / 2),
+        }
+    }
+
+    /// Returns the cell at axial coordinates, or [None] if the hex is outside
+    /// of the grid
+    pub fn hex_to_index(&self, hex: HexCoord) -> Option<CellIndex> {
+        let (col, row) = match self.orientation {
+            HexOrientation::PointyTop => (hex.q + (hex.r - (hex.r & 1)) / 2, hex.r),
+            HexOrientation::FlatTop => (hex.q, hex.r + (hex.q - (hex.q & 1)) / 2),
+        };
+        if col < 0 || row < 0 || col as usize >= self.cols || row as usize >= self.rows {
+            return None;
+        }
+        Some(CellIndex(self.linearize(col as usize, row as usize)))
+    }
+
+    /// Returns the hex containing the given view position, which may be
+    /// outside of the grid
+    pub fn view_to_hex(&self, pos: Vector2<ViewUnits>) -> HexCoord {
+        // Position relative to the center of the first hex, in hex sizes
+        let x = (pos.x - self.rect.origin.x) / self.hex_width;
+        let y = (pos.y - self.rect.origin.y) / self.hex_height;
+        match self.orientation {
+            HexOrientation::PointyTop => {
+                let (x, y) = (x - SQRT_3 / 2.0, y - 1.0);
+                HexCoord::round(SQRT_3 / 3.0 * x - y / 3.0, 2.0 / 3.0 * y)
+            }
+            HexOrientation::FlatTop => {
+                let (x, y) = (x - 1.0, y - SQRT_3 / 2.0);
+                HexCoord::round(2.0 / 3.0 * x, -x / 3.0 + SQRT_3 / 3.0 * y)
+            }
+        }
+    }
+
+    /// Returns the cell containing the given view position, or [None] if
+    /// the position is outside of the grid
+    pub fn view_to_index(&self, pos: Vector2<ViewUnits>) -> Option<CellIndex> {
+        self.hex_to_index(self.view_to_hex(pos))
+    }
+
+    /// Returns the center of a cell in a grid
+    pub fn center_at_index(&self, index: CellIndex) -> Point2<ViewUnits> {
+        self.center_at_hex(self.index_to_hex(index))
+    }
+
+    /// Returns the center of a hex, which may be outside of the grid
+    pub fn center_at_hex(&self, hex: HexCoord) -> Point2<ViewUnits> {
+        let (q, r) = (hex.q as f32, hex.r as f32);
+        let (x, y) = match self.orientation {
+            HexOrientation::PointyTop => (SQRT_3 * (q + r / 2.0) + SQRT_3 / 2.0, 1.5 * r + 1.0),
+            HexOrientation::FlatTop => (1.5 * q + 1.0, SQRT_3 * (r + q / 2.0) + SQRT_3 / 2.0),
+        };
+        point!(
+            self.rect.origin.x + x * self.hex_width,
+            self.rect.origin.y + y * self.hex_height
+        )
+    }
+
+    /// Returns the bounding rect of a cell in a grid
+    pub fn rect_at_index(&self, index: CellIndex) -> Rect<ViewUnits> {
+        let center = self.center_at_index(index);
+        let half = self.unit_cell().size / 2.0;
+        Box2::new(
+            (center.x - half.width, center.y - half.height),
+            (center.x + half.width, center.y + half.height),
+        )
+        .into()
+    }
+
+    /// Returns a single hex-sized bounding rect with minx and miny at zero
+    pub fn unit_cell(&self) -> Rect<ViewUnits> {
+        match self.orientation {
+            HexOrientation::PointyTop => {
+                Rect::from_size(size!(SQRT_3 * self.hex_width, 2.0 * self.hex_height))
+            }
+            HexOrientation::FlatTop => {
+                Rect::from_size(size!(2.0 * self.hex_width, SQRT_3 * self.hex_height))
+            }
+        }
+    }
+
+    /// Returns the squared euclidean distance between two cells, in hexes
+    pub fn distance2(&self, a: CellIndex, b: CellIndex) -> f32 {
+        let d = self.index_to_hex(a) - self.index_to_hex(b);
+        let (q, r) = (d.q as f32, d.r as f32);
+        q * q + r * r + q * r
+    }
+
+    /// Returns the euclidean distance between two cells, in hexes
+    pub fn distance(&self, a: CellIndex, b: CellIndex) -> f32 {
+        self.distance2(a, b).sqrt()
+    }
+
+    /// Returns the number of steps between two cells
+    pub fn grid_distance(&self, a: CellIndex, b: CellIndex) -> usize {
+        self.index_to_hex(a).distance(self.index_to_hex(b)) as usize
+    }
+
+    /// Returns the neighbouring cells, skipping ones outside of the grid
+    pub fn neighbours(&self, index: CellIndex) -> impl Iterator<Item = CellIndex> + '_ {
+        self.index_to_hex(index)
+            .neighbours()
+            .filter_map(|hex| self.hex_to_index(hex))
+    }
+
+    /// Returns cells exactly `radius` steps away, skipping ones outside of
+    /// the grid
+    pub fn ring(&self, index: CellIndex, radius: u32) -> impl Iterator<Item = CellIndex> + '_ {
+        self.index_to_hex(index)
+            .ring(radius)
+            .filter_map(|hex| self.hex_to_index(hex))
+    }
+
+    /// Returns cells up to `radius` steps away, ring by ring, skipping ones
+    /// outside of the grid
+    pub fn spiral(&self, index: CellIndex, radius: u32) -> impl Iterator<Item = CellIndex> + '_ {
+        self.index_to_hex(index)
+            .spiral(radius)
+            .filter_map(|hex| self.hex_to_index(hex))
+    }
+
+    pub fn shape(&self) -> &RuntimeShape<usize, 2> {
+        &self.shape
+    }
+}
+
+impl<ViewUnits: Unit<Scalar = f32>> Debug for HexGridMath<ViewUnits> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HexGridMath")
+            .field("rect", &self.rect)
+            .field("cols", &self.cols)
+            .field("rows", &self.rows)
+            .field("orientation", &self.orientation)
+            .field("hex_width", &self.hex_width)
+            .field("hex_height", &self.hex_height)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use proptest::proptest;
+
+    struct View;
+    impl Unit for View {
+        type Scalar = f32;
+    }
+
+    fn grid(orientation: HexOrientation, swap_y: bool) -> HexGridMath<View> {
+        HexGridMath::new(
+            Rect::new(point!(10.0, 20.0), size!(300.0, 200.0)),
+            7,
+            5,
+            orientation,
+            swap_y,
+        )
+    }
+
+    #[test]
+    fn hex_distance() {
+        let origin = HexCoord::new(0, 0);
+        assert_eq!(origin.distance(HexCoord::new(3, 0)), 3);
+        assert_eq!(origin.distance(HexCoord::new(2, -3)), 3);
+        assert_eq!(origin.distance(HexCoord::new(-2, 4)), 4);
+        assert_eq!(HexCoord::new(1, 1).distance(HexCoord::new(1, 1)), 0);
+    }
+
+    #[test]
+    fn rings_and_spirals() {
+        let center = HexCoord::new(2, -1);
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), vec![center]);
+        for radius in 1..5 {
+            let ring: Vec<_> = center.ring(radius).collect();
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|hex| hex.distance(center) == radius));
+            // Consecutive hexes are neighbours
+            for pair in ring.windows(2) {
+                assert_eq!(pair[0].distance(pair[1]), 1);
+            }
+        }
+        let spiral: Vec<_> = center.spiral(3).collect();
+        assert_eq!(spiral.len(), 1 + 6 + 12 + 18);
+        assert_eq!(spiral[0], center);
+    }
+
+    #[test]
+    fn cube_rounding() {
+        assert_eq!(HexCoord::round(0.1, 0.2), HexCoord::new(0, 0));
+        assert_eq!(HexCoord::round(0.6, 0.1), HexCoord::new(1, 0));
+        assert_eq!(HexCoord::round(0.4, 0.4), HexCoord::new(0, 1));
+        assert_eq!(HexCoord::round(-0.6, 0.5), HexCoord::new(-1, 1));
+    }
+
+    #[test]
+    fn grid_fills_the_rect() {
+        for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+            let grid = grid(orientation, false);
+            let first = grid.rect_at_index(CellIndex(0));
+            assert_relative_eq!(first.origin.x, 10.0, epsilon = 1e-3);
+            assert_relative_eq!(first.origin.y, 20.0, epsilon = 1e-3);
+            let (mut max_x, mut max_y) = (0.0f32, 0.0f32);
+            for i in 0..35 {
+                let rect = grid.rect_at_index(CellIndex(i));
+                max_x = max_x.max(rect.origin.x + rect.width());
+                max_y = max_y.max(rect.origin.y + rect.height());
+            }
+            assert_relative_eq!(max_x, 310.0, epsilon = 1e-3);
+            assert_relative_eq!(max_y, 220.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn offset_coordinates() {
+        let grid = grid(HexOrientation::PointyTop, false);
+        // Second row is shifted right, so its first cell touches both first
+        // cells of the first row
+        let below = grid.hex_to_index(HexCoord::new(0, 1)).unwrap();
+        assert_eq!(below, CellIndex(7));
+        assert_eq!(
+            grid.neighbours(CellIndex(7)).collect::<Vec<_>>(),
+            vec![
+                CellIndex(8),
+                CellIndex(1),
+                CellIndex(0),
+                CellIndex(14),
+                CellIndex(15)
+            ]
+        );
+        assert_eq!(grid.grid_distance(CellIndex(0), CellIndex(34)), 8);
+        assert_eq!(grid.hex_to_index(HexCoord::new(-1, 0)), None);
+        assert_eq!(grid.spiral(CellIndex(0), 1).count(), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn center_round_trip(index in 0usize..35, flat: bool, swap_y: bool) {
+            let orientation = if flat { HexOrientation::FlatTop } else { HexOrientation::PointyTop };
+            let grid = grid(orientation, swap_y);
+            let center = grid.center_at_index(CellIndex(index));
+            assert_eq!(grid.view_to_index(Vector2::new(center.x, center.y)), Some(CellIndex(index)));
+            let hex = grid.index_to_hex(CellIndex(index));
+            assert_eq!(grid.hex_to_index(hex), Some(CellIndex(index)));
+        }
+
+        #[test]
+        fn points_belong_to_closest_hex(x in 10f32..310f32, y in 20f32..220f32, flat: bool) {
+            let orientation = if flat { HexOrientation::FlatTop } else { HexOrientation::PointyTop };
+            let grid = grid(orientation, false);
+            let found = grid.view_to_hex(Vector2::new(x, y));
+            // Distance in hex space, undoing the stretch
+            let hex_distance = |hex: HexCoord| {
+                let center = grid.center_at_hex(hex);
+                let dx = (center.x - x) / grid.hex_width;
+                let dy = (center.y - y) / grid.hex_height;
+                dx * dx + dy * dy
+            };
+            let distance = hex_distance(found);
+            for neighbour in found.neighbours() {
+                assert!(distance <= hex_distance(neighbour) + 1e-3);
+            }
+        }
+    }
+}
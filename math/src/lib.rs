@@ -6,6 +6,13 @@ pub use ndshape;
 
 pub mod arc;
 pub mod board;
+#[cfg(feature = "fixed")]
+pub mod fixed;
 pub mod gravity;
+pub mod hex;
+pub mod scalar;
+pub mod spline;
+pub mod spring;
+pub mod tween;
 
 pub mod lib_ext;
@@ -0,0 +1,134 @@
+use core::fmt::Debug;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Real number type used for gameplay math
+///
+/// Implemented for [f32], and for [crate::fixed::Fixed] with the `fixed`
+/// feature, which gives the same results on every platform
+pub trait Real:
+    Copy
+    + Debug
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const PI: Self;
+    const FRAC_PI_2: Self;
+
+    fn from_f32(value: f32) -> Self;
+    fn to_f32(self) -> f32;
+    fn from_int(value: i32) -> Self;
+    /// Rounds the value down to an integer, NaN is converted to zero
+    fn floor_to_int(self) -> i64;
+
+    fn floor(self) -> Self;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan(self) -> Self;
+    /// Four quadrant arctangent of `self` (y) and `x`
+    fn atan2(self, x: Self) -> Self;
+
+    fn min(self, other: Self) -> Self {
+        if other < self {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn clamp(self, min: Self, max: Self) -> Self {
+        self.max(min).min(max)
+    }
+
+    fn powi(self, n: u32) -> Self {
+        (0..n).fold(Self::ONE, |acc, _| acc * self)
+    }
+}
+
+impl Real for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    const PI: Self = core::f32::consts::PI;
+    const FRAC_PI_2: Self = core::f32::consts::FRAC_PI_2;
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_int(value: i32) -> Self {
+        value as f32
+    }
+
+    fn floor_to_int(self) -> i64 {
+        libm::floorf(self) as i64
+    }
+
+    fn floor(self) -> Self {
+        libm::floorf(self)
+    }
+
+    fn abs(self) -> Self {
+        libm::fabsf(self)
+    }
+
+    fn sqrt(self) -> Self {
+        libm::sqrtf(self)
+    }
+
+    fn sin(self) -> Self {
+        libm::sinf(self)
+    }
+
+    fn cos(self) -> Self {
+        libm::cosf(self)
+    }
+
+    fn asin(self) -> Self {
+        libm::asinf(self)
+    }
+
+    fn acos(self) -> Self {
+        libm::acosf(self)
+    }
+
+    fn atan(self) -> Self {
+        libm::atanf(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        libm::atan2f(self, x)
+    }
+
+    fn min(self, other: Self) -> Self {
+        libm::fminf(self, other)
+    }
+
+    fn max(self, other: Self) -> Self {
+        libm::fmaxf(self, other)
+    }
+
+    fn powi(self, n: u32) -> Self {
+        libm::powf(self, n as f32)
+    }
+}
@@ -0,0 +1,380 @@
+use crate::lib_ext::FloatLibmExt;
+use glam::Vec2;
+
+/// Parametric curve, going from its start at `t = 0` to its end at `t = 1`
+pub trait Curve {
+    fn position(&self, t: f32) -> Vec2;
+
+    /// Derivative of the position by `t`
+    fn tangent(&self, t: f32) -> Vec2;
+
+    /// Normalized direction of movement, or zero if the curve stops at `t`
+    fn direction(&self, t: f32) -> Vec2 {
+        self.tangent(t).normalize_or_zero()
+    }
+
+    /// Angle of the direction of movement, for rotating things along the
+    /// curve
+    fn angle(&self, t: f32) -> f32 {
+        let tangent = self.tangent(t);
+        tangent.y.atan2_m(tangent.x)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QuadraticBezier {
+    pub from: Vec2,
+    pub control: Vec2,
+    pub to: Vec2,
+}
+
+impl QuadraticBezier {
+    pub fn new(from: Vec2, control: Vec2, to: Vec2) -> Self {
+        Self { from, control, to }
+    }
+}
+
+impl Curve for QuadraticBezier {
+    fn position(&self, t: f32) -> Vec2 {
+        let u = 1.0 - t;
+        self.from * (u * u) + self.control * (2.0 * u * t) + self.to * (t * t)
+    }
+
+    fn tangent(&self, t: f32) -> Vec2 {
+        let u = 1.0 - t;
+        (self.control - self.from) * (2.0 * u) + (self.to - self.control) * (2.0 * t)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CubicBezier {
+    pub from: Vec2,
+    pub control_from: Vec2,
+    pub control_to: Vec2,
+    pub to: Vec2,
+}
+
+impl CubicBezier {
+    pub fn new(from: Vec2, control_from: Vec2, control_to: Vec2, to: Vec2) -> Self {
+        Self {
+            from,
+            control_from,
+            control_to,
+            to,
+        }
+    }
+}
+
+impl Curve for CubicBezier {
+    fn position(&self, t: f32) -> Vec2 {
+        let u = 1.0 - t;
+        self.from * u.powi_m(3)
+            + self.control_from * (3.0 * u * u * t)
+            + self.control_to * (3.0 * u * t * t)
+            + self.to * t.powi_m(3)
+    }
+
+    fn tangent(&self, t: f32) -> Vec2 {
+        let u = 1.0 - t;
+        (self.control_from - self.from) * (3.0 * u * u)
+            + (self.control_to - self.control_from) * (6.0 * u * t)
+            + (self.to - self.control_to) * (3.0 * t * t)
+    }
+}
+
+/// Uniform Catmull-Rom spline, passing through every point, like a path
+/// through cell centers
+///
+/// Every segment between two neighbouring points takes the same share of `t`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CatmullRom<'a> {
+    points: &'a [Vec2],
+}
+
+impl<'a> CatmullRom<'a> {
+    /// # Panics
+    /// Panics if there are no points
+    pub fn new(points: &'a [Vec2]) -> Self {
+        assert!(!points.is_empty(), "Spline needs at least one point");
+        Self { points }
+    }
+
+    pub fn points(&self) -> &'a [Vec2] {
+        self.points
+    }
+
+    /// Number of segments between the points
+    pub fn segments(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    /// Returns the segment control points and progress within the segment
+    fn segment_at(&self, t: f32) -> ([Vec2; 4], f32) {
+        let segments = self.segments();
+        if segments == 0 {
+            return ([self.points[0]; 4], 0.0);
+        }
+        let scaled = t.clamp(0.0, 1.0) * segments as f32;
+        let index = (scaled.floor() as usize).min(segments - 1);
+        // Ends are extended by mirroring the neighbouring point, so the
+        // spline keeps its direction there
+        let point = |i: isize| -> Vec2 {
+            if i < 0 {
+                self.points[0] * 2.0 - self.points[1]
+            } else if i as usize > segments {
+                self.points[segments] * 2.0 - self.points[segments - 1]
+            } else {
+                self.points[i as usize]
+            }
+        };
+        let i = index as isize;
+        (
+            [point(i - 1), point(i), point(i + 1), point(i + 2)],
+            scaled - index as f32,
+        )
+    }
+}
+
+impl Curve for CatmullRom<'_> {
+    fn position(&self, t: f32) -> Vec2 {
+        let ([p0, p1, p2, p3], u) = self.segment_at(t);
+        (p1 * 2.0
+            + (p2 - p0) * u
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (u * u)
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (u * u * u))
+            * 0.5
+    }
+
+    fn tangent(&self, t: f32) -> Vec2 {
+        let ([p0, p1, p2, p3], u) = self.segment_at(t);
+        ((p2 - p0)
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * u)
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (3.0 * u * u))
+            * 0.5
+            * self.segments() as f32
+    }
+}
+
+/// Curve sampled by its length, so moving along it at a constant rate of
+/// distance gives constant speed
+///
+/// Lengths are measured at `N` evenly spaced values of `t` and interpolated
+/// in between, so curves with sharp turns need more samples
+#[derive(Debug, Copy, Clone)]
+pub struct ArcLength<C, const N: usize> {
+    curve: C,
+    /// Length of the curve from the start to each sample
+    lengths: [f32; N],
+}
+
+impl<C: Curve, const N: usize> ArcLength<C, N> {
+    /// # Panics
+    /// Panics if `N` is less than 2
+    pub fn new(curve: C) -> Self {
+        assert!(N >= 2, "Arc length needs at least two samples");
+        let mut lengths = [0.0; N];
+        let mut last = curve.position(0.0);
+        for i in 1..N {
+            let pos = curve.position(i as f32 / (N - 1) as f32);
+            lengths[i] = lengths[i - 1] + last.distance(pos);
+            last = pos;
+        }
+        Self { curve, lengths }
+    }
+
+    pub fn curve(&self) -> &C {
+        &self.curve
+    }
+
+    /// Total length of the curve
+    pub fn length(&self) -> f32 {
+        self.lengths[N - 1]
+    }
+
+    /// Returns the curve parameter at the given distance from the start
+    ///
+    /// NaN distances are treated as zero
+    pub fn t_at_distance(&self, distance: f32) -> f32 {
+        let length = self.length();
+        // Written as a negation to also catch NaN
+        if !(length > 0.0 && distance > 0.0) {
+            return 0.0;
+        }
+        if distance >= length {
+            return 1.0;
+        }
+        let after = self.lengths.partition_point(|&l| l < distance);
+        let before = after - 1;
+        let span = self.lengths[after] - self.lengths[before];
+        let local = if span > 0.0 {
+            (distance - self.lengths[before]) / span
+        } else {
+            0.0
+        };
+        (before as f32 + local) / (N - 1) as f32
+    }
+
+    /// Returns the curve parameter at the given fraction of the length
+    pub fn t_at_fraction(&self, fraction: f32) -> f32 {
+        self.t_at_distance(fraction * self.length())
+    }
+
+    pub fn position_at_distance(&self, distance: f32) -> Vec2 {
+        self.curve.position(self.t_at_distance(distance))
+    }
+
+    /// Normalized direction of movement at the given distance
+    pub fn direction_at_distance(&self, distance: f32) -> Vec2 {
+        self.curve.direction(self.t_at_distance(distance))
+    }
+}
+
+/// Curve with its parameter remapped to the fraction of its length
+impl<C: Curve, const N: usize> Curve for ArcLength<C, N> {
+    fn position(&self, t: f32) -> Vec2 {
+        self.curve.position(self.t_at_fraction(t))
+    }
+
+    /// Derivative by the length fraction, so its length stays close to the
+    /// curve length
+    fn tangent(&self, t: f32) -> Vec2 {
+        self.curve.direction(self.t_at_fraction(t)) * self.length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use core::f32::consts::FRAC_PI_2;
+    use glam::vec2;
+    use proptest::proptest;
+
+    fn assert_vec_eq(a: Vec2, b: Vec2) {
+        assert_relative_eq!(a.x, b.x, epsilon = 1e-4);
+        assert_relative_eq!(a.y, b.y, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn bezier_curves() {
+        let quad = QuadraticBezier::new(vec2(0.0, 0.0), vec2(1.0, 2.0), vec2(2.0, 0.0));
+        assert_vec_eq(quad.position(0.0), vec2(0.0, 0.0));
+        assert_vec_eq(quad.position(0.5), vec2(1.0, 1.0));
+        assert_vec_eq(quad.position(1.0), vec2(2.0, 0.0));
+        assert_vec_eq(quad.tangent(0.5), vec2(2.0, 0.0));
+        assert_relative_eq!(quad.angle(0.5), 0.0);
+
+        let cubic = CubicBezier::new(
+            vec2(0.0, 0.0),
+            vec2(0.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 0.0),
+        );
+        assert_vec_eq(cubic.position(0.5), vec2(0.5, 0.75));
+        assert_vec_eq(cubic.tangent(0.0), vec2(0.0, 3.0));
+        assert_relative_eq!(cubic.angle(0.0), FRAC_PI_2);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points() {
+        let points = [
+            vec2(0.0, 0.0),
+            vec2(1.0, 0.0),
+            vec2(1.0, 1.0),
+            vec2(2.0, 1.0),
+        ];
+        let spline = CatmullRom::new(&points);
+        for (i, &point) in points.iter().enumerate() {
+            assert_vec_eq(spline.position(i as f32 / 3.0), point);
+        }
+
+        // Straight paths stay straight at constant speed
+        let line = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)];
+        let spline = CatmullRom::new(&line);
+        assert_vec_eq(spline.position(0.25), vec2(0.5, 0.0));
+        assert_vec_eq(spline.tangent(0.1), vec2(2.0, 0.0));
+        assert_vec_eq(spline.tangent(1.0), vec2(2.0, 0.0));
+
+        let single = [vec2(3.0, 4.0)];
+        assert_vec_eq(CatmullRom::new(&single).position(0.5), vec2(3.0, 4.0));
+    }
+
+    #[test]
+    fn arc_length_gives_constant_speed() {
+        // Control points bunched at the start make the curve slow there
+        let curve = CubicBezier::new(
+            vec2(0.0, 0.0),
+            vec2(0.0, 0.0),
+            vec2(0.0, 0.0),
+            vec2(3.0, 0.0),
+        );
+        let arc = ArcLength::<_, 256>::new(curve);
+        assert_relative_eq!(arc.length(), 3.0, epsilon = 1e-4);
+        assert!(curve.position(0.5).x < 1.0);
+        assert_relative_eq!(arc.position_at_distance(1.5).x, 1.5, epsilon = 1e-2);
+        assert_vec_eq(arc.position(0.0), vec2(0.0, 0.0));
+        assert_vec_eq(arc.position(1.0), vec2(3.0, 0.0));
+        assert_vec_eq(arc.direction_at_distance(1.0), vec2(1.0, 0.0));
+        assert_eq!(arc.t_at_distance(f32::NAN), 0.0);
+        assert_eq!(arc.t_at_distance(f32::NEG_INFINITY), 0.0);
+        assert_eq!(arc.t_at_distance(f32::INFINITY), 1.0);
+
+        let quarter = QuadraticBezier::new(vec2(1.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0));
+        let arc = ArcLength::<_, 128>::new(quarter);
+        assert_relative_eq!(arc.tangent(0.5).length(), arc.length(), epsilon = 1e-4);
+    }
+
+    proptest! {
+        #[test]
+        fn arc_length_is_uniform(
+            x1 in -5f32..5.0,
+            y1 in -5f32..5.0,
+            x2 in -5f32..5.0,
+            y2 in -5f32..5.0,
+        ) {
+            let points = [vec2(0.0, 0.0), vec2(x1, y1), vec2(x2, y2), vec2(4.0, 4.0)];
+            let arc = ArcLength::<_, 1024>::new(CatmullRom::new(&points));
+            // Equal steps along the length cover equal distances, measured
+            // with finer chords so sharp turns don't cut corners
+            let steps = 32;
+            let chords = 64;
+            let step = arc.length() / steps as f32;
+            let mut total = 0.0;
+            for i in 0..steps {
+                let length: f32 = (0..chords)
+                    .map(|j| {
+                        let t = |k: usize| (i * chords + k) as f32 / (steps * chords) as f32;
+                        arc.position(t(j)).distance(arc.position(t(j + 1)))
+                    })
+                    .sum();
+                assert_relative_eq!(length, step, epsilon = 1e-2, max_relative = 0.02);
+                total += length;
+            }
+            assert_relative_eq!(total, arc.length(), max_relative = 0.01);
+        }
+
+        #[test]
+        fn tangents_match_positions(
+            x1 in -5f32..5.0,
+            y1 in -5f32..5.0,
+            x2 in -5f32..5.0,
+            y2 in -5f32..5.0,
+            t in 0.01f32..0.99,
+        ) {
+            let points = [vec2(0.0, 0.0), vec2(x1, y1), vec2(x2, y2), vec2(4.0, 4.0)];
+            let curves: [&dyn Curve; 3] = [
+                &CatmullRom::new(&points),
+                &CubicBezier::new(points[0], points[1], points[2], points[3]),
+                &QuadraticBezier::new(points[0], points[1], points[2]),
+            ];
+            for curve in curves {
+                let h = 1e-3;
+                let numeric = (curve.position(t + h) - curve.position(t - h)) / (2.0 * h);
+                let tangent = curve.tangent(t);
+                // Catmull-Rom segment boundaries may fall between the samples
+                let tolerance = 0.05 * (1.0 + tangent.length());
+                assert!((numeric - tangent).length() <= tolerance);
+            }
+        }
+    }
+}
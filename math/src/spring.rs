@@ -0,0 +1,300 @@
+use crate::lib_ext::FloatLibmExt;
+use core::ops::{Add, Mul, Sub};
+use glam::Vec2;
+
+/// Values that can be moved by a spring
+pub trait SpringValue:
+    Copy + Default + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+    /// Distance from zero, used to check whenever the spring has settled
+    fn magnitude(self) -> f32;
+}
+
+impl SpringValue for f32 {
+    fn magnitude(self) -> f32 {
+        self.abs()
+    }
+}
+
+impl SpringValue for Vec2 {
+    fn magnitude(self) -> f32 {
+        self.length()
+    }
+}
+
+/// Damped harmonic oscillator pulling a value towards a target
+///
+/// Motion is evaluated in closed form, so stepping the spring gives the same
+/// result regardless of the frame rate
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Spring {
+    /// Angular frequency of the undamped oscillation, in radians per second
+    pub frequency: f32,
+    /// Damping ratio, 1 for critical damping, below 1 for oscillations and
+    /// above 1 for slower settling without overshoot
+    pub damping: f32,
+}
+
+/// Position and velocity of a value moved by a spring
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SpringState<T> {
+    pub position: T,
+    pub velocity: T,
+}
+
+impl<T: SpringValue> SpringState<T> {
+    /// State at rest at the given position
+    pub fn at(position: T) -> Self {
+        Self {
+            position,
+            velocity: T::default(),
+        }
+    }
+}
+
+/// Damping ratios this close to 1 are treated as critical
+const CRITICAL_EPSILON: f32 = 1e-4;
+
+impl Spring {
+    pub fn new(frequency: f32, damping: f32) -> Self {
+        Self { frequency, damping }
+    }
+
+    /// Critically damped spring, reaching the target as fast as possible
+    /// without overshooting it
+    pub fn critical(frequency: f32) -> Self {
+        Self::new(frequency, 1.0)
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Returns coefficients `[xx, xv, vx, vv]`, so that after `time` seconds
+    /// the offset from the target is `xx * offset + xv * velocity`, and the
+    /// velocity is `vx * offset + vv * velocity`
+    pub fn coefficients(&self, time: f32) -> [f32; 4] {
+        let w = self.frequency;
+        let z = self.damping;
+        let t = time.max(0.0);
+        if w <= 0.0 {
+            // No spring force at all
+            return [1.0, t, 0.0, 1.0];
+        }
+
+        if (z - 1.0).abs() < CRITICAL_EPSILON {
+            let decay = (-w * t).exp_m();
+            [
+                (1.0 + w * t) * decay,
+                t * decay,
+                -w * w * t * decay,
+                (1.0 - w * t) * decay,
+            ]
+        } else if z < 1.0 {
+            let wd = w * (1.0 - z * z).sqrt();
+            let decay = (-z * w * t).exp_m();
+            let (sin, cos) = (wd * t).sin_cos_m();
+            [
+                decay * (cos + z * w / wd * sin),
+                decay * sin / wd,
+                -decay * w * w / wd * sin,
+                decay * (cos - z * w / wd * sin),
+            ]
+        } else {
+            let root = (z * z - 1.0).sqrt();
+            let r1 = -w * (z - root);
+            let r2 = -w * (z + root);
+            let (e1, e2) = ((r1 * t).exp_m(), (r2 * t).exp_m());
+            let d = r1 - r2;
+            [
+                (-r2 * e1 + r1 * e2) / d,
+                (e1 - e2) / d,
+                r1 * r2 * (e2 - e1) / d,
+                (r1 * e1 - r2 * e2) / d,
+            ]
+        }
+    }
+
+    /// Returns the state after `time` seconds, starting from `state` and
+    /// pulled towards a still `target`
+    pub fn evaluate<T: SpringValue>(
+        &self,
+        state: SpringState<T>,
+        target: T,
+        time: f32,
+    ) -> SpringState<T> {
+        let [xx, xv, vx, vv] = self.coefficients(time);
+        let offset = state.position - target;
+        SpringState {
+            position: target + offset * xx + state.velocity * xv,
+            velocity: offset * vx + state.velocity * vv,
+        }
+    }
+
+    /// Advances the state by `dt` seconds towards the target
+    ///
+    /// The target is assumed to stay still during the step, so moving
+    /// targets are followed with a delay of at most one frame
+    pub fn step<T: SpringValue>(&self, state: &mut SpringState<T>, target: T, dt: f32) {
+        *state = self.evaluate(*state, target, dt);
+    }
+}
+
+/// Value following a target with a spring, like a held gem following the
+/// cursor
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpringFollower<T> {
+    pub spring: Spring,
+    pub state: SpringState<T>,
+    pub target: T,
+}
+
+impl<T: SpringValue> SpringFollower<T> {
+    /// Creates a follower resting at the target
+    pub fn new(spring: Spring, target: T) -> Self {
+        Self {
+            spring,
+            state: SpringState::at(target),
+            target,
+        }
+    }
+
+    pub fn with_state(mut self, state: SpringState<T>) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn value(&self) -> T {
+        self.state.position
+    }
+
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    /// Moves the value to the target at once, stopping the motion
+    pub fn snap(&mut self, target: T) {
+        self.target = target;
+        self.state = SpringState::at(target);
+    }
+
+    /// Advances the motion by `dt` seconds, returning the new value
+    pub fn update(&mut self, dt: f32) -> T {
+        self.spring.step(&mut self.state, self.target, dt);
+        self.state.position
+    }
+
+    /// Checks whenever the value is within `epsilon` of the target and
+    /// moves slower than `epsilon` per second
+    pub fn is_settled(&self, epsilon: f32) -> bool {
+        (self.state.position - self.target).magnitude() <= epsilon
+            && self.state.velocity.magnitude() <= epsilon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use glam::vec2;
+    use proptest::proptest;
+
+    /// Integrates the spring numerically with tiny steps
+    fn simulate(spring: Spring, mut state: SpringState<f32>, target: f32, time: f32) -> f32 {
+        let steps = 100_000;
+        let dt = time / steps as f32;
+        for _ in 0..steps {
+            let force = -spring.frequency * spring.frequency * (state.position - target)
+                - 2.0 * spring.damping * spring.frequency * state.velocity;
+            state.velocity += force * dt;
+            state.position += state.velocity * dt;
+        }
+        state.position
+    }
+
+    #[test]
+    fn starts_in_place() {
+        for damping in [0.3, 1.0, 2.0] {
+            let spring = Spring::new(10.0, damping);
+            assert_eq!(spring.coefficients(0.0), [1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn critical_spring_does_not_overshoot() {
+        let spring = Spring::critical(8.0);
+        let mut state = SpringState::at(0.0);
+        let mut last = 0.0;
+        for _ in 0..120 {
+            spring.step(&mut state, 1.0, 1.0 / 60.0);
+            assert!(state.position >= last);
+            assert!(state.position <= 1.0);
+            last = state.position;
+        }
+        assert_relative_eq!(state.position, 1.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn underdamped_spring_overshoots() {
+        let spring = Spring::new(8.0, 0.2);
+        let peak = (0..120)
+            .map(|i| {
+                spring
+                    .evaluate(SpringState::at(0.0), 1.0, i as f32 / 60.0)
+                    .position
+            })
+            .fold(0.0, f32::max);
+        assert!(peak > 1.3);
+    }
+
+    #[test]
+    fn matches_numeric_integration() {
+        let start = SpringState {
+            position: 2.0,
+            velocity: -3.0,
+        };
+        for damping in [0.2, 1.0, 1.8] {
+            let spring = Spring::new(6.0, damping);
+            let exact = spring.evaluate(start, 0.5, 0.7).position;
+            assert_relative_eq!(exact, simulate(spring, start, 0.5, 0.7), epsilon = 1e-2);
+        }
+    }
+
+    #[test]
+    fn follows_vector_targets() {
+        let mut follower = SpringFollower::new(Spring::critical(20.0), Vec2::ZERO);
+        follower.set_target(vec2(10.0, -5.0));
+        assert!(!follower.is_settled(0.01));
+        for _ in 0..120 {
+            follower.update(1.0 / 60.0);
+        }
+        assert!(follower.is_settled(0.01));
+        assert_relative_eq!(follower.value().x, 10.0, epsilon = 1e-2);
+
+        follower.snap(Vec2::ONE);
+        assert_eq!(follower.update(1.0), Vec2::ONE);
+    }
+
+    proptest! {
+        #[test]
+        fn frame_rate_independent(
+            frequency in 0.5f32..30.0,
+            damping in 0.05f32..3.0,
+            position in -10f32..10.0,
+            velocity in -50f32..50.0,
+            fps in 10u32..240,
+        ) {
+            let spring = Spring::new(frequency, damping);
+            let start = SpringState { position, velocity };
+            let mut stepped = start;
+            for _ in 0..fps {
+                spring.step(&mut stepped, 1.0, 1.0 / fps as f32);
+            }
+            let exact = spring.evaluate(start, 1.0, 1.0);
+            let scale = 1.0 + position.abs() + velocity.abs();
+            assert_relative_eq!(stepped.position, exact.position, epsilon = 1e-3 * scale);
+            assert_relative_eq!(stepped.velocity, exact.velocity, epsilon = 1e-2 * scale * frequency);
+        }
+    }
+}
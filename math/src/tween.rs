@@ -0,0 +1,544 @@
+use glam::{Vec2, Vec3, Vec4};
+
+/// Values that can be interpolated
+pub trait Lerp: Copy {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec2::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec3::lerp(self, to, t)
+    }
+}
+
+/// Colors are usually stored as RGBA vectors
+impl Lerp for Vec4 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec4::lerp(self, to, t)
+    }
+}
+
+/// RGBA color
+impl Lerp for [f32; 4] {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec4::from(self).lerp(Vec4::from(to), t).into()
+    }
+}
+
+/// Easing function, mapping progress in `0..=1` to eased progress
+pub type Easing = fn(f32) -> f32;
+
+/// Common easing functions
+pub mod easing {
+    use crate::lib_ext::FloatLibmExt;
+    use core::f32::consts::PI;
+
+    pub fn linear(t: f32) -> f32 {
+        t
+    }
+
+    pub fn quad_in(t: f32) -> f32 {
+        t * t
+    }
+
+    pub fn quad_out(t: f32) -> f32 {
+        1.0 - (1.0 - t) * (1.0 - t)
+    }
+
+    pub fn quad_in_out(t: f32) -> f32 {
+        if t < 0.5 {
+            2.0 * t * t
+        } else {
+            1.0 - (-2.0 * t + 2.0).powi_m(2) / 2.0
+        }
+    }
+
+    pub fn cubic_in(t: f32) -> f32 {
+        t * t * t
+    }
+
+    pub fn cubic_out(t: f32) -> f32 {
+        1.0 - (1.0 - t).powi_m(3)
+    }
+
+    pub fn cubic_in_out(t: f32) -> f32 {
+        if t < 0.5 {
+            4.0 * t * t * t
+        } else {
+            1.0 - (-2.0 * t + 2.0).powi_m(3) / 2.0
+        }
+    }
+
+    pub fn sine_in_out(t: f32) -> f32 {
+        -((PI * t).cos_m() - 1.0) / 2.0
+    }
+
+    /// Overshoots the target slightly before settling
+    pub fn back_out(t: f32) -> f32 {
+        const C1: f32 = 1.70158;
+        const C3: f32 = C1 + 1.0;
+        1.0 + C3 * (t - 1.0).powi_m(3) + C1 * (t - 1.0).powi_m(2)
+    }
+
+    /// Bounces off the target like a dropped ball
+    pub fn bounce_out(t: f32) -> f32 {
+        const N1: f32 = 7.5625;
+        const D1: f32 = 2.75;
+        if t < 1.0 / D1 {
+            N1 * t * t
+        } else if t < 2.0 / D1 {
+            let t = t - 1.5 / D1;
+            N1 * t * t + 0.75
+        } else if t < 2.5 / D1 {
+            let t = t - 2.25 / D1;
+            N1 * t * t + 0.9375
+        } else {
+            let t = t - 2.625 / D1;
+            N1 * t * t + 0.984375
+        }
+    }
+}
+
+/// Value changing over time, that can be sampled at any point
+///
+/// Time is measured in seconds from the start of the animation. Sampling
+/// before the start returns the initial value, and sampling after the end
+/// returns the final one
+pub trait Animation {
+    type Value;
+
+    /// Total duration of the animation, infinite for endless loops
+    fn duration(&self) -> f32;
+
+    fn sample(&self, time: f32) -> Self::Value;
+
+    fn is_finished(&self, time: f32) -> bool {
+        time >= self.duration()
+    }
+
+    /// Plays `next` after this animation
+    fn then<B: Animation<Value = Self::Value>>(self, next: B) -> Sequence<Self, B>
+    where
+        Self: Sized,
+    {
+        Sequence {
+            first: self,
+            second: next,
+        }
+    }
+
+    /// Plays `other` at the same time as this animation, producing both
+    /// values
+    fn with<B: Animation>(self, other: B) -> Parallel<Self, B>
+    where
+        Self: Sized,
+    {
+        Parallel { a: self, b: other }
+    }
+
+    /// Holds the initial value for `delay` seconds before starting
+    fn delayed(self, delay: f32) -> Delay<Self>
+    where
+        Self: Sized,
+    {
+        Delay { delay, inner: self }
+    }
+
+    /// Plays the animation `count` times in a row
+    fn repeat(self, count: u32) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            count: Some(count),
+            ping_pong: false,
+        }
+    }
+
+    /// Plays the animation in an endless loop
+    fn repeat_forever(self) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            count: None,
+            ping_pong: false,
+        }
+    }
+
+    /// Plays the animation forth and back `count` times
+    fn ping_pong(self, count: u32) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            count: Some(count),
+            ping_pong: true,
+        }
+    }
+
+    /// Plays the animation forth and back in an endless loop
+    fn ping_pong_forever(self) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            count: None,
+            ping_pong: true,
+        }
+    }
+
+    /// Transforms sampled values
+    fn map<T, F: Fn(Self::Value) -> T>(self, map: F) -> Map<Self, F>
+    where
+        Self: Sized,
+    {
+        Map { inner: self, map }
+    }
+}
+
+/// Interpolates between two values
+#[derive(Debug, Copy, Clone)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: easing::linear,
+        }
+    }
+
+    /// Tween that holds the value for the duration
+    pub fn hold(value: T, duration: f32) -> Self {
+        Self::new(value, value, duration)
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Returns linear progress of the tween at the given time
+    pub fn progress(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return if time < 0.0 { 0.0 } else { 1.0 };
+        }
+        (time / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+impl<T: Lerp> Animation for Tween<T> {
+    type Value = T;
+
+    fn duration(&self) -> f32 {
+        self.duration.max(0.0)
+    }
+
+    fn sample(&self, time: f32) -> T {
+        self.from.lerp(self.to, (self.easing)(self.progress(time)))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Sequence<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Animation, B: Animation<Value = A::Value>> Animation for Sequence<A, B> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        self.first.duration() + self.second.duration()
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        let first = self.first.duration();
+        if time < first {
+            self.first.sample(time)
+        } else {
+            self.second.sample(time - first)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Parallel<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: Animation, B: Animation> Animation for Parallel<A, B> {
+    type Value = (A::Value, B::Value);
+
+    fn duration(&self) -> f32 {
+        self.a.duration().max(self.b.duration())
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        (self.a.sample(time), self.b.sample(time))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Delay<A> {
+    delay: f32,
+    inner: A,
+}
+
+impl<A: Animation> Animation for Delay<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        self.delay + self.inner.duration()
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        self.inner.sample((time - self.delay).max(0.0))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Repeat<A> {
+    inner: A,
+    /// Number of runs, or [None] for endless loops
+    count: Option<u32>,
+    /// Whenever every run goes forth and back
+    ping_pong: bool,
+}
+
+impl<A: Animation> Repeat<A> {
+    fn run_duration(&self) -> f32 {
+        let duration = self.inner.duration();
+        if self.ping_pong {
+            duration * 2.0
+        } else {
+            duration
+        }
+    }
+}
+
+impl<A: Animation> Animation for Repeat<A> {
+    type Value = A::Value;
+
+    fn duration(&self) -> f32 {
+        match self.count {
+            Some(count) => self.run_duration() * count as f32,
+            None => f32::INFINITY,
+        }
+    }
+
+    fn sample(&self, time: f32) -> Self::Value {
+        let run = self.run_duration();
+        let inner = self.inner.duration();
+        if run <= 0.0 || !run.is_finite() || time <= 0.0 {
+            return self.inner.sample(time);
+        }
+        if self.count.is_some_and(|count| time >= run * count as f32) {
+            // Finished runs stay at their end, which is the start for
+            // ping-pong
+            return self.inner.sample(if self.ping_pong { 0.0 } else { inner });
+        }
+        let local = time % run;
+        if self.ping_pong && local > inner {
+            self.inner.sample(run - local)
+        } else {
+            self.inner.sample(local)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Map<A, F> {
+    inner: A,
+    map: F,
+}
+
+impl<A: Animation, T, F: Fn(A::Value) -> T> Animation for Map<A, F> {
+    type Value = T;
+
+    fn duration(&self) -> f32 {
+        self.inner.duration()
+    }
+
+    fn sample(&self, time: f32) -> T {
+        (self.map)(self.inner.sample(time))
+    }
+}
+
+/// Animation anchored to an absolute start time, like the game clock
+#[derive(Debug, Copy, Clone)]
+pub struct Timeline<A> {
+    pub start: f64,
+    pub animation: A,
+}
+
+impl<A: Animation> Timeline<A> {
+    pub fn new(start: f64, animation: A) -> Self {
+        Self { start, animation }
+    }
+
+    /// Absolute time at which the animation ends
+    pub fn end(&self) -> f64 {
+        self.start + self.animation.duration() as f64
+    }
+
+    /// Samples the animation at the absolute time
+    pub fn sample_at(&self, now: f64) -> A::Value {
+        self.animation.sample((now - self.start) as f32)
+    }
+
+    pub fn is_finished_at(&self, now: f64) -> bool {
+        self.animation.is_finished((now - self.start) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use glam::vec2;
+    use proptest::proptest;
+
+    const EASINGS: [Easing; 9] = [
+        easing::linear,
+        easing::quad_in,
+        easing::quad_out,
+        easing::quad_in_out,
+        easing::cubic_in,
+        easing::cubic_out,
+        easing::cubic_in_out,
+        easing::sine_in_out,
+        easing::bounce_out,
+    ];
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        for easing in EASINGS.into_iter().chain([easing::back_out as Easing]) {
+            assert_relative_eq!(easing(0.0), 0.0, epsilon = 1e-5);
+            assert_relative_eq!(easing(1.0), 1.0, epsilon = 1e-5);
+        }
+        assert_relative_eq!(easing::quad_in_out(0.5), 0.5);
+        assert_relative_eq!(easing::sine_in_out(0.5), 0.5);
+        assert!(easing::back_out(0.8) > 1.0);
+    }
+
+    #[test]
+    fn tween_clamps_time() {
+        let tween = Tween::new(vec2(0.0, 10.0), vec2(4.0, 20.0), 2.0);
+        assert_eq!(tween.sample(-1.0), vec2(0.0, 10.0));
+        assert_eq!(tween.sample(1.0), vec2(2.0, 15.0));
+        assert_eq!(tween.sample(5.0), vec2(4.0, 20.0));
+        assert!(!tween.is_finished(1.9));
+        assert!(tween.is_finished(2.0));
+
+        let eased = Tween::new(0.0, 1.0, 2.0).with_easing(easing::quad_in);
+        assert_relative_eq!(eased.sample(1.0), 0.25);
+
+        let instant = Tween::new(0.0, 1.0, 0.0);
+        assert_eq!(instant.sample(0.0), 1.0);
+    }
+
+    #[test]
+    fn sequences_and_delays() {
+        let animation = Tween::new(0.0, 1.0, 1.0)
+            .then(Tween::hold(1.0, 0.5))
+            .then(Tween::new(1.0, 0.0, 1.0))
+            .delayed(1.0);
+        assert_eq!(animation.duration(), 3.5);
+        assert_eq!(animation.sample(0.5), 0.0);
+        assert_eq!(animation.sample(1.5), 0.5);
+        assert_eq!(animation.sample(2.25), 1.0);
+        assert_eq!(animation.sample(3.0), 0.5);
+        assert_eq!(animation.sample(10.0), 0.0);
+    }
+
+    #[test]
+    fn parallel_groups() {
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let faded = [1.0, 1.0, 1.0, 0.0];
+        let animation = Tween::new(Vec2::ZERO, vec2(2.0, 0.0), 1.0)
+            .with(Tween::new(color, faded, 2.0))
+            .map(|(pos, color)| (pos.x, color[3]));
+        assert_eq!(animation.duration(), 2.0);
+        assert_eq!(animation.sample(0.5), (1.0, 0.75));
+        assert_eq!(animation.sample(1.5), (2.0, 0.25));
+    }
+
+    #[test]
+    fn loops() {
+        let repeated = Tween::new(0.0, 1.0, 1.0).repeat(3);
+        assert_eq!(repeated.duration(), 3.0);
+        assert_eq!(repeated.sample(0.25), 0.25);
+        assert_eq!(repeated.sample(2.25), 0.25);
+        assert_eq!(repeated.sample(4.0), 1.0);
+
+        let forever = Tween::new(0.0, 1.0, 1.0).repeat_forever();
+        assert_eq!(forever.duration(), f32::INFINITY);
+        assert_eq!(forever.sample(1000.5), 0.5);
+        assert!(!forever.is_finished(1e9));
+
+        let ping_pong = Tween::new(0.0, 1.0, 1.0).ping_pong(2);
+        assert_eq!(ping_pong.duration(), 4.0);
+        assert_eq!(ping_pong.sample(0.25), 0.25);
+        assert_eq!(ping_pong.sample(1.25), 0.75);
+        assert_eq!(ping_pong.sample(2.5), 0.5);
+        assert_eq!(ping_pong.sample(5.0), 0.0);
+
+        let breathing = Tween::new(1.0, 1.5, 0.5).ping_pong_forever();
+        assert_eq!(breathing.sample(100.75), 1.25);
+    }
+
+    #[test]
+    fn timelines() {
+        let timeline = Timeline::new(100.0, Tween::new(0.0, 1.0, 2.0).delayed(1.0));
+        assert_eq!(timeline.end(), 103.0);
+        assert_eq!(timeline.sample_at(50.0), 0.0);
+        assert_eq!(timeline.sample_at(102.0), 0.5);
+        assert!(!timeline.is_finished_at(102.0));
+        assert!(timeline.is_finished_at(103.0));
+    }
+
+    proptest! {
+        #[test]
+        fn sequence_is_continuous(split in 0.1f32..5.0, time in 0f32..10.0) {
+            let animation = Tween::new(0.0, 1.0, split).then(Tween::new(1.0, 3.0, 2.0));
+            let value = animation.sample(time);
+            assert!((0.0..=3.0).contains(&value));
+            // Values near the seam match
+            let before = animation.sample(split - 1e-4);
+            let after = animation.sample(split + 1e-4);
+            assert_relative_eq!(before, after, epsilon = 1e-2);
+        }
+
+        #[test]
+        fn easings_stay_close_to_range(t in 0f32..=1.0, easing in 0usize..9) {
+            let value = EASINGS[easing](t);
+            assert!((-1e-5..=1.0 + 1e-5).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{ActionId, GemColorId, RandomTableId, Registry, RegistryError};
+use luck::pool::RandomPool;
+use luck::weight::WeightError;
+use luck::LuckState;
+use scrapcore_serialization::derive::DatabaseModel;
+use scrapcore_serialization::registry::index::RegistryIndex;
+use scrapcore_serialization::registry::PartialRegistry;
+use scrapcore_serialization::serialization::error::{
+    DeserializationError, DeserializationErrorKind,
+};
+use scrapcore_serialization::serialization::{DeserializeModel, SerializationFallback};
+
+/// Weighted table of items, used for things like gem spawn rates
+#[derive(Debug, DatabaseModel)]
+pub struct RandomTableModel {
+    pub entries: RandomTableEntries,
+}
+
+#[derive(Debug, DatabaseModel)]
+pub struct RandomTableEntry {
+    /// Relative chance of the entry, compared to other entries of the table
+    pub weight: u64,
+    pub item: RandomTableItem,
+}
+
+#[derive(Debug, Copy, Clone, DatabaseModel)]
+#[model_serde(tag = "type")]
+pub enum RandomTableItem {
+    /// Entry that yields no item
+    Nothing,
+    GemColor {
+        id: GemColorId,
+    },
+    Action {
+        id: ActionId,
+    },
+    /// Rolls another table in place of this entry
+    Table {
+        id: RandomTableId,
+    },
+}
+
+/// Entries of a table, along with the pool that is built once when the
+/// table gets loaded
+///
+/// Tables without entries or with all weights being zero fail to load
+#[derive(Debug)]
+pub struct RandomTableEntries {
+    entries: Vec<RandomTableEntry>,
+    pool: RandomPool<RandomTableItem>,
+}
+
+impl RandomTableEntries {
+    pub fn entries(&self) -> &[RandomTableEntry] {
+        &self.entries
+    }
+}
+
+impl RandomTableModel {
+    /// Returns the pool of the table entries
+    ///
+    /// Nested tables are kept as is, use [RandomTableModel::roll] to resolve
+    /// them
+    pub fn pool(&self) -> &RandomPool<RandomTableItem> {
+        &self.entries.pool
+    }
+
+    /// Picks an item from the table, rolling nested tables until an item or
+    /// nothing is picked
+    ///
+    /// Never returns [RandomTableItem::Table]. Tables leading back to
+    /// themselves are rejected when the mod is loaded, so nesting always ends
+    pub fn roll(&self, registry: &Registry, state: &mut LuckState) -> RandomTableItem {
+        let mut table = self;
+        loop {
+            match *table.pool().get(state) {
+                RandomTableItem::Table { id } => {
+                    table = RegistryIndex::<RandomTableModel>::get(&id, registry);
+                }
+                item => return item,
+            }
+        }
+    }
+
+    /// Checks whenever every item the table can roll passes the check,
+    /// looking through nested tables
+    ///
+    /// Entries with zero weight are never rolled, so they are skipped. Tables
+    /// should be checked for cycles first, see [validate_random_tables]
+    pub fn only_rolls(
+        &self,
+        registry: &Registry,
+        check: &impl Fn(&RandomTableItem) -> bool,
+    ) -> bool {
+        self.entries
+            .entries
+            .iter()
+            .filter(|entry| entry.weight > 0)
+            .all(|entry| match entry.item {
+                RandomTableItem::Table { id } => {
+                    RegistryIndex::<RandomTableModel>::get(&id, registry)
+                        .only_rolls(registry, check)
+                }
+                item => check(&item),
+            })
+    }
+
+    /// Returns IDs of the tables nested directly into this one
+    fn nested(&self) -> impl Iterator<Item = RandomTableId> + '_ {
+        self.entries
+            .entries
+            .iter()
+            .filter_map(|entry| match entry.item {
+                RandomTableItem::Table { id } => Some(id),
+                _ => None,
+            })
+    }
+}
+
+/// Checks that none of the tables lead back to themselves through nested
+/// tables
+pub(crate) fn validate_random_tables(registry: &Registry) -> Result<(), RegistryError> {
+    fn visit<'a>(
+        table: &'a RandomTableModel,
+        registry: &'a Registry,
+        path: &mut Vec<&'a RandomTableModel>,
+        checked: &mut Vec<&'a RandomTableModel>,
+    ) -> Result<(), RegistryError> {
+        if checked.iter().any(|&t| std::ptr::eq(t, table)) {
+            return Ok(());
+        }
+        path.push(table);
+        for id in table.nested() {
+            let nested = RegistryIndex::<RandomTableModel>::get(&id, registry);
+            if path.iter().any(|&t| std::ptr::eq(t, nested)) {
+                return Err(RegistryError::RandomTableCycle(id));
+            }
+            visit(nested, registry, path, checked)?;
+        }
+        path.pop();
+        checked.push(table);
+        Ok(())
+    }
+
+    let mut checked = vec![];
+    for (_, table) in registry.random_table.iter() {
+        visit(&table.data, registry, &mut vec![], &mut checked)?;
+    }
+    Ok(())
+}
+
+// <================================>
+// <===== Deserialization code =====>
+// <================================>
+
+impl SerializationFallback for RandomTableEntries {
+    type Fallback = Vec<RandomTableEntrySerialized>;
+}
+
+impl<Registry: PartialRegistry> DeserializeModel<RandomTableEntries, Registry>
+    for Vec<RandomTableEntrySerialized>
+where
+    Registry::Error: From<WeightError>,
+    RandomTableEntrySerialized: DeserializeModel<RandomTableEntry, Registry>,
+{
+    fn deserialize(
+        self,
+        registry: &mut Registry,
+    ) -> Result<RandomTableEntries, DeserializationError<Registry>> {
+        let entries: Vec<RandomTableEntry> = self
+            .into_iter()
+            .map(|entry| DeserializeModel::deserialize(entry, registry))
+            .collect::<Result<_, _>>()?;
+        let pool = RandomPool::try_new(
+            entries.iter().map(|e| e.item).collect(),
+            entries.iter().map(|e| e.weight).collect(),
+        )
+        .map_err(|e| DeserializationErrorKind::Custom(Registry::Error::from(e)))?;
+
+        Ok(RandomTableEntries { entries, pool })
+    }
+}
@@ -6,6 +6,7 @@ mod assets {
 mod collections {
     pub mod actions;
     pub mod gem;
+    pub mod random_table;
 }
 
 mod singletons {
@@ -25,6 +26,7 @@ pub use assets::sprite::{SpriteData, SpriteId};
 
 pub use collections::actions::{ActionEffect, ActionOrChain, ChainType, CombatActionChain};
 pub use collections::gem::{GemColorModel, GemModifierModel};
+pub use collections::random_table::{RandomTableEntry, RandomTableItem, RandomTableModel};
 
 pub use singletons::settings::ModSettings;
 
@@ -1,6 +1,8 @@
 use crate::assets::sprite::TextureCache;
+use crate::collections::random_table::validate_random_tables;
 use crate::loading::staggerer::{Staggerer, StaggererImpl};
 use crate::registry::{PartialRegistry, Registry, RegistryItemSerialized};
+use crate::singletons::settings::validate_settings;
 use crate::SpriteId;
 use assets_manager::loader::{BytesLoader, LoadFrom};
 use assets_manager::source::Source;
@@ -147,6 +149,8 @@ impl<'a, S: Source> LoadedMod<'a, S> {
         }
 
         let registry = reg.into_registry()?;
+        validate_random_tables(&registry)?;
+        validate_settings(&registry)?;
 
         let mod_data = Self {
             registry,
@@ -1,5 +1,6 @@
 use crate::assets::sprite::SpriteId;
-use crate::{collections, singletons};
+use crate::{collections, singletons, RandomTableId};
+use luck::weight::WeightError;
 use scrapcore_serialization::derive::registry;
 use scrapcore_serialization::registry::SerializationRegistry;
 use scrapcore_serialization::serialization::error::{
@@ -18,6 +19,9 @@ pub enum Registry {
     #[model(collection)]
     GemColor(collections::gem::GemColorModel),
 
+    #[model(collection)]
+    RandomTable(collections::random_table::RandomTableModel),
+
     #[model(asset)]
     Textures(SpriteId),
 }
@@ -26,6 +30,12 @@ pub enum Registry {
 pub enum RegistryError {
     #[error("{}", .0)]
     ParseColorError(csscolorparser::ParseColorError),
+    #[error("{}", .0)]
+    WeightError(WeightError),
+    #[error("Random table {:?} leads back to itself through nested tables", .0)]
+    RandomTableCycle(RandomTableId),
+    #[error("Gem spawns table {:?} can roll items other than gem colors", .0)]
+    GemSpawnsNotGems(RandomTableId),
 }
 
 impl From<csscolorparser::ParseColorError> for RegistryError {
@@ -34,6 +44,12 @@ impl From<csscolorparser::ParseColorError> for RegistryError {
     }
 }
 
+impl From<WeightError> for RegistryError {
+    fn from(err: WeightError) -> Self {
+        Self::WeightError(err)
+    }
+}
+
 impl<Registry: SerializationRegistry<Error = RegistryError>> From<RegistryError>
     for DeserializationError<Registry>
 {
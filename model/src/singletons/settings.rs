@@ -1,7 +1,24 @@
-use crate::SpriteData;
+use crate::collections::random_table::{RandomTableItem, RandomTableModel};
+use crate::{RandomTableId, Registry, RegistryError, SpriteData};
 use scrapcore_serialization::derive::DatabaseModel;
+use scrapcore_serialization::registry::index::RegistryIndex;
 
 #[derive(Debug, DatabaseModel)]
 pub struct ModSettings {
     pub logo: SpriteData,
+    /// Table of gems spawned on the board
+    pub gem_spawns: RandomTableId,
+}
+
+/// Checks that the gem spawns table can only roll gem colors, so the board
+/// never gets filled with empty cells
+pub(crate) fn validate_settings(registry: &Registry) -> Result<(), RegistryError> {
+    let id = registry.settings.gem_spawns;
+    let spawns = RegistryIndex::<RandomTableModel>::get(&id, registry);
+    if !spawns.only_rolls(registry, &|item| {
+        matches!(item, RandomTableItem::GemColor { .. })
+    }) {
+        return Err(RegistryError::GemSpawnsNotGems(id));
+    }
+    Ok(())
 }